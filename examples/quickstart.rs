@@ -1,7 +1,7 @@
 use std::thread::sleep;
 use std::time::Duration;
 
-use rppal::spi::{Bus, Mode, SlaveSelect, Spi};
+use rppal::spi::{Bus, Mode, SimpleHalSpiDevice, SlaveSelect, Spi};
 
 use ls7366::Ls7366;
 
@@ -12,7 +12,7 @@ fn main() {
     let spi_1 = Spi::new(Bus::Spi0, SlaveSelect::Ss1, 14_000_000, Mode::Mode0).unwrap();
 
     // Construct a driver instance from the SPI interface, using default chip configurations.
-    let mut spi_driver = Ls7366::new(spi_1).unwrap();
+    let mut spi_driver = Ls7366::new(SimpleHalSpiDevice::new(spi_1)).unwrap();
 
     // Loop and read the counter.
     loop {
@@ -21,4 +21,4 @@ fn main() {
         println!("read data:= {:?}\n status := {:?}", result, status);
         sleep(Duration::from_secs(1));
     }
-}
\ No newline at end of file
+}
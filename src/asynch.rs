@@ -0,0 +1,377 @@
+//! Async counterpart of [`Ls7366`], built on `embedded_hal_async`'s [`SpiDevice`].
+//!
+//! Enabled with the `async` cargo feature.
+//!
+//! [`Ls7366Async`] offers the same register level API as the blocking driver, with every
+//! operation that touches the bus being an `async fn`. Both drivers share the [`ir`], [`mdr0`],
+//! [`mdr1`] and [`str_register`] codecs.
+//!
+//! [`Ls7366`]: ../struct.Ls7366.html
+//! [`Ls7366Async`]: ./struct.Ls7366Async.html
+//! [`SpiDevice`]: https://docs.rs/embedded-hal-async/1.0.0/embedded_hal_async/spi/trait.SpiDevice.html
+//! [`ir`]: ../ir/index.html
+//! [`mdr0`]: ../mdr0/index.html
+//! [`mdr1`]: ../mdr1/index.html
+//! [`str_register`]: ../str_register/index.html
+
+use embedded_hal_async::spi::{Operation, SpiDevice};
+
+use crate::events::Events;
+use crate::ir::{Action, InstructionRegister, Target};
+use crate::registers::{self, Registers};
+use crate::str_register::Str;
+use crate::traits::{Decodable, Encodable};
+use crate::{mdr0, mdr1, ConfigurationMismatch, Error, Snapshot};
+
+/// An LS7366 Quadrature encoder buffer driven through an async SPI interface.
+pub struct Ls7366Async<SPI> {
+    /// SPI interface where the buffer is attached.
+    interface: SPI,
+    /// Last values written into the configuration registers and [`Dtr`].
+    ///
+    /// [`Dtr`]:  ../ir/enum.Target.html#variant.Dtr
+    registers: Registers,
+}
+
+impl<SPI, SpiError> Ls7366Async<SPI>
+    where SPI: SpiDevice<u8, Error=SpiError> {
+    /// Creates a new driver and initializes the chip exactly like [`Ls7366::new`].
+    ///
+    /// [`Ls7366::new`]: ../struct.Ls7366.html#method.new
+    pub async fn new(iface: SPI) -> Result<Self, Error<SpiError>> {
+        let mut driver = Ls7366Async::new_uninit(iface);
+        driver.configure(registers::INITIAL_MDR0, registers::INITIAL_MDR1).await?;
+        driver.set_count(0).await?;
+        driver.clear_status().await?;
+        Ok(driver)
+    }
+
+    /// Creates a new driver but does NOT do any initialization actions against the chip.
+    pub fn new_uninit(iface: SPI) -> Self {
+        Ls7366Async {
+            interface: iface,
+            registers: Registers::default(),
+        }
+    }
+
+    /// Writes both configuration registers to the chip and caches them in the driver.
+    pub async fn configure(&mut self, mdr0: mdr0::Mdr0, mdr1: mdr1::Mdr1) -> Result<(), Error<SpiError>> {
+        self.write_mdr0(mdr0).await?;
+        self.write_mdr1(mdr1).await
+    }
+
+    /// Returns the cached primary configuration.
+    pub fn mdr0(&self) -> mdr0::Mdr0 {
+        self.registers.mdr0
+    }
+
+    /// Returns the cached secondary configuration.
+    pub fn mdr1(&self) -> mdr1::Mdr1 {
+        self.registers.mdr1
+    }
+
+    /// Changes the quadrature count mode, preserving the rest of the cached [`Mdr0`].
+    ///
+    /// [`Mdr0`]: ../mdr0/struct.Mdr0.html
+    pub async fn set_quad_mode(&mut self, quad_count_mode: mdr0::QuadCountMode) -> Result<(), Error<SpiError>> {
+        let mut mdr0 = self.registers.mdr0;
+        mdr0.quad_count_mode = quad_count_mode;
+        self.write_mdr0(mdr0).await
+    }
+
+    /// Changes the cycle count mode, preserving the rest of the cached [`Mdr0`].
+    ///
+    /// [`Mdr0`]: ../mdr0/struct.Mdr0.html
+    pub async fn set_cycle_count_mode(&mut self, cycle_count_mode: mdr0::CycleCountMode) -> Result<(), Error<SpiError>> {
+        let mut mdr0 = self.registers.mdr0;
+        mdr0.cycle_count_mode = cycle_count_mode;
+        self.write_mdr0(mdr0).await
+    }
+
+    /// Changes the behavior of the index pin, preserving the rest of the cached [`Mdr0`].
+    ///
+    /// [`Mdr0`]: ../mdr0/struct.Mdr0.html
+    pub async fn set_index_mode(&mut self, index_mode: mdr0::IndexMode) -> Result<(), Error<SpiError>> {
+        let mut mdr0 = self.registers.mdr0;
+        mdr0.index_mode = index_mode;
+        self.write_mdr0(mdr0).await
+    }
+
+    /// Changes the index filter clock, preserving the rest of the cached [`Mdr0`].
+    ///
+    /// [`Mdr0`]: ../mdr0/struct.Mdr0.html
+    pub async fn set_filter_clock(&mut self, filter_clock: mdr0::FilterClockDivisionFactor) -> Result<(), Error<SpiError>> {
+        let mut mdr0 = self.registers.mdr0;
+        mdr0.filter_clock = filter_clock;
+        self.write_mdr0(mdr0).await
+    }
+
+    /// Changes the counter width, preserving the rest of the cached [`Mdr1`].
+    ///
+    /// [`Mdr1`]: ../mdr1/struct.Mdr1.html
+    pub async fn set_counter_mode(&mut self, counter_mode: mdr1::CounterMode) -> Result<(), Error<SpiError>> {
+        let mut mdr1 = self.registers.mdr1;
+        mdr1.counter_mode = counter_mode;
+        self.write_mdr1(mdr1).await
+    }
+
+    /// Enables (false) or disables (true) counting, preserving the rest of the cached [`Mdr1`].
+    ///
+    /// [`Mdr1`]: ../mdr1/struct.Mdr1.html
+    pub async fn set_disable_counting(&mut self, disable_counting: bool) -> Result<(), Error<SpiError>> {
+        let mut mdr1 = self.registers.mdr1;
+        mdr1.disable_counting = disable_counting;
+        self.write_mdr1(mdr1).await
+    }
+
+    /// Reads the primary configuration back from the chip.
+    pub async fn read_mdr0(&mut self) -> Result<mdr0::Mdr0, Error<SpiError>> {
+        let raw_result: &mut [u8] = &mut [0x00];
+        let raw_result = self.read_register(raw_result, Target::Mdr0).await?;
+        mdr0::Mdr0::decode(raw_result[0]).map_err(Error::EncodeError)
+    }
+
+    /// Reads the secondary configuration back from the chip.
+    pub async fn read_mdr1(&mut self) -> Result<mdr1::Mdr1, Error<SpiError>> {
+        let raw_result: &mut [u8] = &mut [0x00];
+        let raw_result = self.read_register(raw_result, Target::Mdr1).await?;
+        mdr1::Mdr1::decode(raw_result[0]).map_err(Error::EncodeError)
+    }
+
+    /// Compares the chip's configuration registers against the cached configuration, see
+    /// [`Ls7366::verify_configuration`].
+    ///
+    /// [`Ls7366::verify_configuration`]: ../struct.Ls7366.html#method.verify_configuration
+    pub async fn verify_configuration(&mut self) -> Result<(), Error<SpiError>> {
+        let actual_mdr0 = self.read_mdr0().await?;
+        let actual_mdr1 = self.read_mdr1().await?;
+        if actual_mdr0 == self.registers.mdr0 && actual_mdr1 == self.registers.mdr1 {
+            Ok(())
+        } else {
+            Err(Error::ConfigurationMismatch(ConfigurationMismatch {
+                expected_mdr0: self.registers.mdr0,
+                actual_mdr0,
+                expected_mdr1: self.registers.mdr1,
+                actual_mdr1,
+            }))
+        }
+    }
+
+    /// Writes `value` into [`Dtr`] using exactly as many bytes as the configured counter width.
+    ///
+    /// [`Dtr`]:  ../ir/enum.Target.html#variant.Dtr
+    pub async fn write_dtr(&mut self, value: u32) -> Result<(), Error<SpiError>> {
+        let bytes = self.registers.encode_dtr(value).ok_or(Error::ValueOutOfRange)?;
+        self.write_register(Target::Dtr, &bytes[4 - self.registers.width()..]).await
+    }
+
+    /// Returns the value last written into [`Dtr`].
+    ///
+    /// [`Dtr`]:  ../ir/enum.Target.html#variant.Dtr
+    pub fn read_dtr(&self) -> u32 {
+        self.registers.dtr
+    }
+
+    /// Sets the counter to `value` through [`Dtr`], see [`Ls7366::set_count`]. In the range-limit
+    /// and modulo-N cycle count modes `value` must not exceed the limit held in [`Dtr`], which is
+    /// restored afterwards.
+    ///
+    /// [`Dtr`]:  ../ir/enum.Target.html#variant.Dtr
+    /// [`Ls7366::set_count`]: ../struct.Ls7366.html#method.set_count
+    pub async fn set_count(&mut self, value: i64) -> Result<(), Error<SpiError>> {
+        let limit = self.registers.dtr;
+        self.write_signed_dtr(value).await?;
+        self.act(InstructionRegister {
+            target: Target::Cntr,
+            action: Action::Load,
+        }, &mut [0x00]).await?;
+        if self.registers.mdr0.cycle_count_mode.is_bounded() {
+            self.write_dtr(limit).await?;
+        }
+        Ok(())
+    }
+
+    /// Clears the [`Cntr`] counter register to zero.
+    ///
+    /// [`Cntr`]: ../ir/enum.Target.html#variant.Cntr
+    pub async fn clear_count(&mut self) -> Result<(), Error<SpiError>> {
+        self.act(InstructionRegister {
+            target: Target::Cntr,
+            action: Action::Clear,
+        }, &mut [0x00]).await?;
+        Ok(())
+    }
+
+    /// Arranges for the counter to be set to `value` on the next index pulse.
+    pub async fn preset_count_on_index(&mut self, value: i64) -> Result<(), Error<SpiError>> {
+        self.write_signed_dtr(value).await?;
+        self.set_index_mode(mdr0::IndexMode::LoadCntr).await
+    }
+
+    /// Counts between zero and `max` in range-limit mode, see [`Ls7366::configure_range_limit`].
+    ///
+    /// [`Ls7366::configure_range_limit`]: ../struct.Ls7366.html#method.configure_range_limit
+    pub async fn configure_range_limit(&mut self, max: u32) -> Result<(), Error<SpiError>> {
+        self.write_dtr(max).await?;
+        self.set_cycle_count_mode(mdr0::CycleCountMode::RangeLimit).await
+    }
+
+    /// Counts modulo `n`, see [`Ls7366::configure_modulo`].
+    ///
+    /// [`Ls7366::configure_modulo`]: ../struct.Ls7366.html#method.configure_modulo
+    pub async fn configure_modulo(&mut self, n: u32) -> Result<(), Error<SpiError>> {
+        let max = n.checked_sub(1).ok_or(Error::ValueOutOfRange)?;
+        self.write_dtr(max).await?;
+        self.set_cycle_count_mode(mdr0::CycleCountMode::ModuloN).await
+    }
+
+    /// Writes bytes into the specified register. attempting to write more than 4 bytes is an error.
+    pub async fn write_register(&mut self, target: Target, data: &[u8]) -> Result<(), Error<SpiError>> {
+        if data.len() > 4 {
+            return Err(Error::PayloadTooBig);
+        }
+        let registers = self.registers.after_write(&target, data).map_err(Error::EncodeError)?;
+        let encoded = InstructionRegister {
+            target,
+            action: Action::Write,
+        }.encode();
+        let payload: &mut [u8] = &mut [encoded, encoded, encoded, encoded, encoded];
+        payload[1..=data.len()].copy_from_slice(data);
+
+        self.interface.write(&payload[0..data.len() + 1]).await?;
+        self.registers = registers;
+        Ok(())
+    }
+
+    /// Executes a read operation against specified register, filling `rx_buffer` with up to
+    /// 4 bytes from the chip.
+    pub async fn read_register<'a>(&mut self, rx_buffer: &'a mut [u8], target: Target) -> Result<&'a [u8], Error<SpiError>> {
+        if rx_buffer.len() > 4 {
+            return Err(Error::PayloadTooBig);
+        }
+        let ir = InstructionRegister {
+            target,
+            action: Action::Read,
+        };
+        self.interface.transaction(&mut [
+            Operation::Write(&[ir.encode()]),
+            Operation::Read(rx_buffer),
+        ]).await?;
+        Ok(rx_buffer)
+    }
+
+    pub async fn get_status(&mut self) -> Result<Str, Error<SpiError>> {
+        let result: &mut [u8] = &mut [0x00, 0x00, 0x00, 0x00];
+        let raw_result = self.read_register(result, Target::Str).await?;
+        Str::decode(raw_result[3]).map_err(Error::EncodeError)
+    }
+
+    /// Clears the [`Str`] status register to zero.
+    ///
+    /// [`Str`]:  ../ir/enum.Target.html#variant.Str
+    pub async fn clear_status(&mut self) -> Result<(), Error<SpiError>> {
+        self.act(InstructionRegister {
+            target: Target::Str,
+            action: Action::Clear,
+        }, &mut [0x00]).await?;
+        Ok(())
+    }
+
+    /// Reads the chip's current count, sign-extended from the configured counter width.
+    pub async fn get_count(&mut self) -> Result<i64, Error<SpiError>> {
+        self.read_count_register(Target::Cntr).await
+    }
+
+    /// Latches the instantaneous value of [`Cntr`] into [`Otr`] without disturbing counting.
+    ///
+    /// [`Cntr`]: ../ir/enum.Target.html#variant.Cntr
+    /// [`Otr`]:  ../ir/enum.Target.html#variant.Otr
+    pub async fn latch_count(&mut self) -> Result<(), Error<SpiError>> {
+        self.act(InstructionRegister {
+            target: Target::Otr,
+            action: Action::Load,
+        }, &mut [0x00]).await?;
+        Ok(())
+    }
+
+    /// Reads the count previously latched into [`Otr`].
+    ///
+    /// [`Otr`]:  ../ir/enum.Target.html#variant.Otr
+    pub async fn read_latched_count(&mut self) -> Result<i64, Error<SpiError>> {
+        self.read_count_register(Target::Otr).await
+    }
+
+    /// Latches the count and captures the status register alongside it.
+    pub async fn snapshot(&mut self) -> Result<Snapshot, Error<SpiError>> {
+        self.latch_count().await?;
+        let status = self.get_status().await?;
+        let count = self.read_latched_count().await?;
+        Ok(Snapshot { count, status })
+    }
+
+    /// Reads and clears the [`Str`] latches, returning the events recorded since the last poll.
+    ///
+    /// As with [`Ls7366::poll_events`], [`Str`] is only cleared when something is latched, and an
+    /// event latched between the read and the clear is lost.
+    ///
+    /// [`Str`]: ../ir/enum.Target.html#variant.Str
+    /// [`Ls7366::poll_events`]: ../struct.Ls7366.html#method.poll_events
+    pub async fn poll_events(&mut self) -> Result<Events, Error<SpiError>> {
+        let status = self.get_status().await?;
+        let events = Events::from_status(&status);
+        if !events.is_empty() {
+            self.clear_status().await?;
+        }
+        Ok(events)
+    }
+
+    /// Performs a transaction against the chip, see [`Ls7366::act`].
+    ///
+    /// [`Ls7366::act`]: ../struct.Ls7366.html#method.act
+    pub async fn act<'a>(&mut self, command: InstructionRegister, data: &'a mut [u8]) -> Result<&'a [u8], Error<SpiError>> {
+        let tx_buffer: &[u8] = &[command.encode()];
+        match command.action {
+            Action::Clear | Action::Load => {
+                if data.len() > 1 {
+                    return Err(Error::PayloadTooBig);
+                }
+                self.interface.write(tx_buffer).await?;
+            }
+            Action::Read => {
+                let tx_buffer = [tx_buffer[0], 0x00, 0x00, 0x00, 0x00];
+                self.interface.transfer(data, &tx_buffer).await?;
+            }
+            Action::Write => {
+                if data.len() > 5 {
+                    return Err(Error::PayloadTooBig);
+                }
+                self.interface.write(tx_buffer).await?;
+            }
+        }
+        Ok(data)
+    }
+
+    async fn read_count_register(&mut self, target: Target) -> Result<i64, Error<SpiError>> {
+        let raw_result: &mut [u8] = &mut [0x00, 0x00, 0x00, 0x00];
+        let width = self.registers.width();
+        let raw_result = self.read_register(&mut raw_result[..width], target).await?;
+        Ok(self.registers.decode_count(raw_result))
+    }
+
+    async fn write_signed_dtr(&mut self, value: i64) -> Result<(), Error<SpiError>> {
+        let raw = self.registers.encode_count(value).ok_or(Error::ValueOutOfRange)?;
+        self.write_dtr(raw).await
+    }
+
+    async fn write_mdr0(&mut self, mdr0: mdr0::Mdr0) -> Result<(), Error<SpiError>> {
+        self.write_register(Target::Mdr0, &[mdr0.encode()]).await?;
+        self.registers.mdr0 = mdr0;
+        Ok(())
+    }
+
+    async fn write_mdr1(&mut self, mdr1: mdr1::Mdr1) -> Result<(), Error<SpiError>> {
+        self.write_register(Target::Mdr1, &[mdr1.encode()]).await?;
+        self.registers.mdr1 = mdr1;
+        Ok(())
+    }
+}
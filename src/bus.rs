@@ -0,0 +1,144 @@
+//! Sharing one SPI bus between several chips.
+//!
+//! [`Ls7366`] takes ownership of its SPI interface. To place several chips on one bus (such as
+//! the dual LS7366R breakout), wrap the bus in a [`SharedBus`] and hand each driver a
+//! [`BusDevice`], which pairs the shared bus with that chip's select pin.
+//!
+//! Every transaction through a [`BusDevice`] asserts its own select pin, performs all of its
+//! operations and releases the pin again, so only one chip is ever selected at a time. The
+//! [`SpiBus`] given to [`SharedBus`] must therefore not drive any select line itself.
+//!
+//! ```
+//! use ls7366::Ls7366;
+//! use ls7366::bus::SharedBus;
+//! # use embedded_hal_mock::eh1::spi::Mock;
+//! # use embedded_hal_mock::eh1::digital::{Mock as PinMock, State, Transaction as PinTransaction};
+//! # let mut spi = Mock::new(&[]);
+//! # let mut x_select = PinMock::new(&[PinTransaction::set(State::High)]);
+//! # let mut y_select = PinMock::new(&[PinTransaction::set(State::High)]);
+//! # let (bus_spi, x_pin, y_pin) = (spi.clone(), x_select.clone(), y_select.clone());
+//! // --- snip ---
+//!     let bus = SharedBus::new(bus_spi);
+//!     let mut x_axis = Ls7366::new_uninit(bus.device(x_pin).unwrap());
+//!     let mut y_axis = Ls7366::new_uninit(bus.device(y_pin).unwrap());
+//! # spi.done();
+//! # x_select.done();
+//! # y_select.done();
+//! ```
+//!
+//! [`Ls7366`]: ../struct.Ls7366.html
+//! [`SharedBus`]: ./struct.SharedBus.html
+//! [`BusDevice`]: ./struct.BusDevice.html
+//! [`SpiBus`]: https://docs.rs/embedded-hal/1.0.0/embedded_hal/spi/trait.SpiBus.html
+
+use core::cell::RefCell;
+
+use embedded_hal::digital::OutputPin;
+use embedded_hal::spi::{self, ErrorKind, ErrorType, Operation, SpiBus, SpiDevice};
+
+#[derive(Clone, Debug)]
+pub enum BusError<SpiError, PinError> {
+    // Underlying SPI interface error
+    Spi(SpiError),
+    // Failed to drive a chip select pin
+    Pin(PinError),
+    // The bus is already in use by another device, e.g. from an interrupt handler.
+    Busy,
+    // The transaction contains a delay operation, which the bus cannot time.
+    DelayUnsupported,
+}
+
+impl<SpiError, PinError> spi::Error for BusError<SpiError, PinError>
+    where SpiError: spi::Error,
+          PinError: core::fmt::Debug {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            BusError::Spi(error) => error.kind(),
+            BusError::Pin(_) => ErrorKind::ChipSelectFault,
+            BusError::Busy | BusError::DelayUnsupported => ErrorKind::Other,
+        }
+    }
+}
+
+/// An SPI bus shared between several devices.
+pub struct SharedBus<BUS> {
+    bus: RefCell<BUS>,
+}
+
+impl<BUS> SharedBus<BUS> {
+    pub fn new(bus: BUS) -> Self {
+        SharedBus {
+            bus: RefCell::new(bus)
+        }
+    }
+
+    /// Creates a device on this bus selected by `cs`.
+    ///
+    /// The select pin is driven high (inactive) straight away so the chip cannot pick up traffic
+    /// meant for another device.
+    pub fn device<CS: OutputPin>(&self, mut cs: CS) -> Result<BusDevice<'_, BUS, CS>, CS::Error> {
+        cs.set_high()?;
+        Ok(BusDevice {
+            bus: &self.bus,
+            cs,
+        })
+    }
+
+    /// Releases the underlying SPI bus.
+    pub fn into_inner(self) -> BUS {
+        self.bus.into_inner()
+    }
+}
+
+/// One device on a [`SharedBus`], usable as the SPI interface of an [`Ls7366`].
+///
+/// Delay operations are not supported: a transaction containing one is rejected with
+/// [`BusError::DelayUnsupported`] before the chip is selected.
+///
+/// [`SharedBus`]: ./struct.SharedBus.html
+/// [`Ls7366`]: ../struct.Ls7366.html
+/// [`BusError::DelayUnsupported`]: ./enum.BusError.html#variant.DelayUnsupported
+pub struct BusDevice<'a, BUS, CS> {
+    bus: &'a RefCell<BUS>,
+    cs: CS,
+}
+
+impl<'a, BUS, CS> BusDevice<'a, BUS, CS> {
+    /// Releases the select pin.
+    pub fn into_inner(self) -> CS {
+        self.cs
+    }
+}
+
+impl<'a, BUS, CS> ErrorType for BusDevice<'a, BUS, CS>
+    where BUS: ErrorType,
+          CS: OutputPin {
+    type Error = BusError<BUS::Error, CS::Error>;
+}
+
+impl<'a, BUS, CS> SpiDevice<u8> for BusDevice<'a, BUS, CS>
+    where BUS: SpiBus<u8>,
+          CS: OutputPin {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+        if operations.iter().any(|operation| matches!(operation, Operation::DelayNs(_))) {
+            return Err(BusError::DelayUnsupported);
+        }
+        let mut bus = self.bus.try_borrow_mut().map_err(|_| BusError::Busy)?;
+        self.cs.set_low().map_err(BusError::Pin)?;
+
+        let result = operations.iter_mut().try_for_each(|operation| match operation {
+            Operation::Read(words) => bus.read(words),
+            Operation::Write(words) => bus.write(words),
+            Operation::Transfer(read, write) => bus.transfer(read, write),
+            Operation::TransferInPlace(words) => bus.transfer_in_place(words),
+            // Rejected above.
+            Operation::DelayNs(_) => Ok(()),
+        });
+        // Let the bus finish clocking out even if an operation failed, then deselect the chip so
+        // it does not pick up other traffic.
+        let flushed = bus.flush();
+        let deselected = self.cs.set_high();
+        result.and(flushed).map_err(BusError::Spi)?;
+        deselected.map_err(BusError::Pin)
+    }
+}
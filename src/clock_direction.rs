@@ -0,0 +1,130 @@
+//! Counting clock and direction signals, e.g. to monitor step/dir pulses sent to a motor driver.
+//!
+//! In [`QuadCountMode::NonQuad`] the chip counts once on every rising edge of A, upwards while
+//! B is high and downwards while it is low. [`MDR0`] and [`MDR1`] configure the chip for this
+//! with the full counter width, and [`PulseRate`] measures the pulse frequency from sampled
+//! counts, like a gated frequency counter.
+//!
+//! ```
+//! use core::cell::RefCell;
+//! use ls7366::Ls7366;
+//! use ls7366::clock_direction::{PulseRate, MDR0, MDR1};
+//! use ls7366::emulator::{EmulatorDevice, Ls7366Emulator};
+//! use ls7366::generator::{ConstantVelocity, QuadratureGenerator, Signalling};
+//!
+//! let chip = RefCell::new(Ls7366Emulator::new());
+//! let mut driver = Ls7366::new(EmulatorDevice::new(&chip)).unwrap();
+//! driver.configure(MDR0, MDR1).unwrap();
+//! let mut steps = QuadratureGenerator::new(Signalling::ClockDirection);
+//!
+//! // Sample every millisecond, measuring over 10ms windows.
+//! let mut rate = PulseRate::new(MDR1.counter_mode, 10_000);
+//! for tick in 0..=20 {
+//!     rate.update(tick * 1000, driver.get_count().unwrap());
+//!     // 3 steps per millisecond, backwards.
+//!     for inputs in steps.run(ConstantVelocity::new(-3, 1)) {
+//!         chip.borrow_mut().apply(inputs);
+//!     }
+//! }
+//! assert_eq!(rate.rate(), Some(-3000.0));
+//! ```
+//!
+//! [`QuadCountMode::NonQuad`]: ../mdr0/enum.QuadCountMode.html#variant.NonQuad
+//! [`MDR0`]: ./constant.MDR0.html
+//! [`MDR1`]: ./constant.MDR1.html
+//! [`PulseRate`]: ./struct.PulseRate.html
+
+use crate::mdr0::{CycleCountMode, FilterClockDivisionFactor, IndexMode, Mdr0, QuadCountMode};
+use crate::mdr1::{CounterMode, Mdr1};
+use crate::utilities;
+
+/// Primary configuration for clock and direction inputs: non-quadrature, free running, with
+/// the index input disabled.
+pub const MDR0: Mdr0 = Mdr0 {
+    quad_count_mode: QuadCountMode::NonQuad,
+    cycle_count_mode: CycleCountMode::FreeRunning,
+    index_mode: IndexMode::DisableIndex,
+    is_index_inverted: false,
+    filter_clock: FilterClockDivisionFactor::One,
+};
+
+/// Secondary configuration for clock and direction inputs: full 4 byte counter, no flags.
+pub const MDR1: Mdr1 = Mdr1 {
+    counter_mode: CounterMode::Byte4,
+    disable_counting: false,
+    flag_on_idx: false,
+    flag_on_cmp: false,
+    flag_on_bw: false,
+    flag_on_cy: false,
+};
+
+/// Measures the pulse rate over gate windows of a fixed minimum length.
+///
+/// The caller samples the count and supplies the time of each sample in microseconds from any
+/// monotonic clock. Once a sample is at least the gate time after the start of the window, the
+/// pulses counted in between give the rate and a new window starts at that sample. Longer gates
+/// resolve the rate more finely but update less often.
+///
+/// Counter wraps are undone as long as the counter moves by less than half its range between
+/// samples, as for [`PositionTracker`].
+///
+/// [`PositionTracker`]: ../tracker/struct.PositionTracker.html
+#[derive(Clone, Debug)]
+pub struct PulseRate {
+    counter_mode: CounterMode,
+    gate_us: u64,
+    /// Timestamp of the window's first sample, timestamp and count of the last sample, and the
+    /// pulses counted since the first.
+    window: Option<(u64, u64, i64, i64)>,
+    rate: Option<f32>,
+}
+
+impl PulseRate {
+    /// Creates a meter for counts read with `counter_mode`, measuring over at least `gate_us`
+    /// microseconds.
+    pub fn new(counter_mode: CounterMode, gate_us: u64) -> Self {
+        PulseRate {
+            counter_mode,
+            gate_us,
+            window: None,
+            rate: None,
+        }
+    }
+
+    /// Takes a count sampled at `timestamp_us` and returns the latest rate, in pulses per
+    /// second with counting down being negative.
+    ///
+    /// A sample not newer than the previous one is ignored, as for the [`Estimator`]s.
+    ///
+    /// [`Estimator`]: ../velocity/trait.Estimator.html
+    pub fn update(&mut self, timestamp_us: u64, count: i64) -> Option<f32> {
+        let (start, last_count, pulses) = match self.window {
+            None => {
+                self.window = Some((timestamp_us, timestamp_us, count, 0));
+                return self.rate;
+            }
+            Some((_, last_timestamp, _, _)) if timestamp_us <= last_timestamp => return self.rate,
+            Some((start, _, last_count, pulses)) => (start, last_count, pulses),
+        };
+        let pulses = pulses + utilities::shortest_distance(last_count, count, self.counter_mode.byte_count());
+        let elapsed = timestamp_us - start;
+        if elapsed >= self.gate_us {
+            self.rate = Some(pulses as f32 * 1e6 / elapsed as f32);
+            self.window = Some((timestamp_us, timestamp_us, count, 0));
+        } else {
+            self.window = Some((start, timestamp_us, count, pulses));
+        }
+        self.rate
+    }
+
+    /// The rate measured over the last complete window.
+    pub fn rate(&self) -> Option<f32> {
+        self.rate
+    }
+
+    /// Forgets the current window and the last rate, e.g. after the count was changed.
+    pub fn reset(&mut self) {
+        self.window = None;
+        self.rate = None;
+    }
+}
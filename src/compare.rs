@@ -0,0 +1,100 @@
+//! Sequenced position triggers on top of the compare latch.
+//!
+//! [`Ls7366::arm_compare`] arms a single trigger position. A [`CompareSequence`] walks through a
+//! list of them, re-arming the next position as soon as the previous one was reached, e.g. to
+//! fire a camera at fixed points along a move.
+//!
+//! ```
+//! use core::cell::RefCell;
+//! use ls7366::Ls7366;
+//! use ls7366::compare::CompareSequence;
+//! use ls7366::emulator::{EmulatorDevice, Ls7366Emulator};
+//! use ls7366::generator::{ConstantVelocity, QuadratureGenerator, Signalling};
+//!
+//! let chip = RefCell::new(Ls7366Emulator::new());
+//! let mut driver = Ls7366::new(EmulatorDevice::new(&chip)).unwrap();
+//! let mut generator = QuadratureGenerator::new(Signalling::Quadrature);
+//!
+//! let mut triggers = CompareSequence::new(&[100, 250, 400]);
+//! triggers.start(&mut driver).unwrap();
+//! let mut reached = Vec::new();
+//! for _ in 0..50 {
+//!     for inputs in generator.run(ConstantVelocity::new(10, 1)) {
+//!         chip.borrow_mut().apply(inputs);
+//!     }
+//!     if let Some(position) = triggers.poll(&mut driver).unwrap() {
+//!         reached.push(position);
+//!     }
+//! }
+//! assert_eq!(reached, [100, 250, 400]);
+//! assert!(triggers.is_done());
+//! ```
+//!
+//! [`Ls7366::arm_compare`]: ../struct.Ls7366.html#method.arm_compare
+//! [`CompareSequence`]: ./struct.CompareSequence.html
+
+use embedded_hal::spi::SpiDevice;
+
+use crate::{Error, Ls7366};
+
+/// A list of compare positions, armed one after the other.
+///
+/// Each position must be reached before the encoder passes the next one, as only one compare
+/// position is armed at a time.
+#[derive(Clone, Debug)]
+pub struct CompareSequence<'p> {
+    positions: &'p [i64],
+    /// Index of the armed position, `positions.len()` once all were reached.
+    armed: usize,
+}
+
+impl<'p> CompareSequence<'p> {
+    pub fn new(positions: &'p [i64]) -> Self {
+        CompareSequence { positions, armed: 0 }
+    }
+
+    /// Arms the first position. Returns it, or `None` for an empty sequence.
+    pub fn start<SPI, SpiError>(&mut self, driver: &mut Ls7366<SPI>) -> Result<Option<i64>, Error<SpiError>>
+        where SPI: SpiDevice<u8, Error=SpiError> {
+        self.armed = 0;
+        self.arm(driver)
+    }
+
+    /// Checks whether the armed position was reached and if so arms the next one.
+    ///
+    /// Returns the position that was reached.
+    pub fn poll<SPI, SpiError>(&mut self, driver: &mut Ls7366<SPI>) -> Result<Option<i64>, Error<SpiError>>
+        where SPI: SpiDevice<u8, Error=SpiError> {
+        let reached = match self.positions.get(self.armed) {
+            Some(position) => *position,
+            None => return Ok(None),
+        };
+        if !driver.check_compare()? {
+            return Ok(None);
+        }
+        self.armed += 1;
+        self.arm(driver)?;
+        Ok(Some(reached))
+    }
+
+    /// The position currently armed.
+    pub fn armed(&self) -> Option<i64> {
+        self.positions.get(self.armed).copied()
+    }
+
+    /// Whether every position has been reached.
+    pub fn is_done(&self) -> bool {
+        self.armed >= self.positions.len()
+    }
+
+    fn arm<SPI, SpiError>(&mut self, driver: &mut Ls7366<SPI>) -> Result<Option<i64>, Error<SpiError>>
+        where SPI: SpiDevice<u8, Error=SpiError> {
+        match self.armed() {
+            Some(position) => {
+                driver.arm_compare(position)?;
+                Ok(Some(position))
+            }
+            None => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,243 @@
+//! Support for SPI interfaces implementing the embedded-hal 0.2 blocking traits.
+//!
+//! Enabled with the `embedded-hal-02` cargo feature.
+//!
+//! Wrap such an interface in an [`Eh02Spi`] to use it with [`Ls7366`]:
+//! ```
+//! use ls7366::Ls7366;
+//! use ls7366::eh02::Eh02Spi;
+//! # use embedded_hal_mock::eh0::spi::Mock;
+//! # let mut spi_02 = Mock::new(&[]);
+//! // --- snip ---
+//!     let mut driver = Ls7366::new_uninit(Eh02Spi::new(spi_02.clone()));
+//! # spi_02.done();
+//! ```
+//!
+//! Code written against the embedded-hal 0.2 [`Qei`] trait can use the chip through a
+//! [`Eh02Qei`].
+//!
+//! [`Eh02Spi`]: ./struct.Eh02Spi.html
+//! [`Ls7366`]: ../struct.Ls7366.html
+//! [`Qei`]: https://docs.rs/embedded-hal/0.2.7/embedded_hal/trait.Qei.html
+//! [`Eh02Qei`]: ./struct.Eh02Qei.html
+
+use core::cell::{Cell, RefCell};
+
+use embedded_hal::spi::{self, ErrorKind, ErrorType, Operation, SpiDevice};
+use embedded_hal_02::blocking::spi::{Transfer, Write};
+use embedded_hal_02::{Direction, Qei};
+
+use crate::events::Events;
+use crate::str_register;
+use crate::{Error, Ls7366};
+
+/// Errors raised by an [`Eh02Spi`].
+///
+/// [`Eh02Spi`]: ./struct.Eh02Spi.html
+#[derive(Clone, Debug)]
+pub enum Eh02Error<E> {
+    /// Error raised by the wrapped embedded-hal 0.2 interface.
+    Spi(E),
+    /// The transaction moves more than [`MAX_TRANSACTION`] bytes.
+    ///
+    /// [`MAX_TRANSACTION`]: ./constant.MAX_TRANSACTION.html
+    TransactionTooLong,
+    /// The transaction contains a delay, which cannot be expressed as a single transfer.
+    DelayUnsupported,
+}
+
+impl<E: core::fmt::Debug> spi::Error for Eh02Error<E> {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+/// Largest number of bytes a transaction through an [`Eh02Spi`] may move. [`Ls7366`] never
+/// moves more than 5.
+///
+/// [`Eh02Spi`]: ./struct.Eh02Spi.html
+/// [`Ls7366`]: ../struct.Ls7366.html
+pub const MAX_TRANSACTION: usize = 16;
+
+/// Presents an embedded-hal 0.2 blocking SPI interface as an embedded-hal 1.0 [`SpiDevice`].
+///
+/// embedded-hal 0.2 interfaces typically select the chip for the duration of each individual
+/// call. [`Ls7366`] reads a register with a write of the instruction followed by a read of
+/// the data, and the chip aborts the read if it is deselected in between, so each transaction
+/// is gathered into one buffer and clocked out as a single `transfer`, or a single `write` if
+/// nothing is read. Transactions are therefore limited to [`MAX_TRANSACTION`] bytes, and delay
+/// operations are rejected with [`Eh02Error::DelayUnsupported`] before anything is sent.
+///
+/// [`SpiDevice`]: https://docs.rs/embedded-hal/1.0.0/embedded_hal/spi/trait.SpiDevice.html
+/// [`Ls7366`]: ../struct.Ls7366.html
+/// [`MAX_TRANSACTION`]: ./constant.MAX_TRANSACTION.html
+/// [`Eh02Error::DelayUnsupported`]: ./enum.Eh02Error.html#variant.DelayUnsupported
+pub struct Eh02Spi<SPI> {
+    spi: SPI,
+}
+
+impl<SPI> Eh02Spi<SPI> {
+    pub fn new(spi: SPI) -> Self {
+        Eh02Spi { spi }
+    }
+
+    /// Releases the wrapped interface.
+    pub fn into_inner(self) -> SPI {
+        self.spi
+    }
+}
+
+impl<SPI, E> ErrorType for Eh02Spi<SPI>
+    where SPI: Transfer<u8, Error=E> + Write<u8, Error=E>,
+          E: core::fmt::Debug {
+    type Error = Eh02Error<E>;
+}
+
+impl<SPI, E> SpiDevice<u8> for Eh02Spi<SPI>
+    where SPI: Transfer<u8, Error=E> + Write<u8, Error=E>,
+          E: core::fmt::Debug {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+        let mut buffer = [0x00; MAX_TRANSACTION];
+        let mut length = 0;
+        let mut reads = false;
+        for operation in operations.iter() {
+            let (words, size): (&[u8], usize) = match operation {
+                Operation::Read(words) => {
+                    reads = true;
+                    (&[], words.len())
+                }
+                Operation::Write(words) => (words, words.len()),
+                Operation::Transfer(read, write) => {
+                    reads = true;
+                    (write, read.len().max(write.len()))
+                }
+                Operation::TransferInPlace(words) => {
+                    reads = true;
+                    (words, words.len())
+                }
+                Operation::DelayNs(_) => return Err(Eh02Error::DelayUnsupported),
+            };
+            let segment = buffer.get_mut(length..length + size).ok_or(Eh02Error::TransactionTooLong)?;
+            // Reads clock out zeros, and transfers pad a short write with zeros.
+            segment[..words.len()].copy_from_slice(words);
+            length += size;
+        }
+
+        if !reads {
+            return self.spi.write(&buffer[..length]).map_err(Eh02Error::Spi);
+        }
+        self.spi.transfer(&mut buffer[..length]).map_err(Eh02Error::Spi)?;
+
+        let mut received = &buffer[..length];
+        for operation in operations.iter_mut() {
+            let size = match operation {
+                Operation::Read(words) | Operation::TransferInPlace(words) => {
+                    words.copy_from_slice(&received[..words.len()]);
+                    words.len()
+                }
+                Operation::Write(words) => words.len(),
+                Operation::Transfer(read, write) => {
+                    read.copy_from_slice(&received[..read.len()]);
+                    read.len().max(write.len())
+                }
+                Operation::DelayNs(_) => 0,
+            };
+            received = &received[size..];
+        }
+        Ok(())
+    }
+}
+
+impl From<str_register::Direction> for Direction {
+    fn from(direction: str_register::Direction) -> Self {
+        match direction {
+            str_register::Direction::Up => Direction::Upcounting,
+            str_register::Direction::Down => Direction::Downcounting,
+        }
+    }
+}
+
+/// Implements the embedded-hal 0.2 [`Qei`] trait on top of an [`Ls7366`].
+///
+/// `Count` is `i64` and holds what [`Ls7366::get_count`] returns for the configured
+/// [`CounterMode`]: sign-extended from the counter width, so it wraps between `-2^(8n-1)` and
+/// `2^(8n-1) - 1` for an `n` byte counter, or unsigned within `0..=Dtr` in the range-limit and
+/// modulo-N cycle count modes. Differences between counts taken across a wrap have to be
+/// reduced modulo `2^(8n)` by the consumer; a 4 byte counter makes wraps least likely.
+///
+/// The trait's methods take `&self` and cannot fail, so the driver is kept in a [`RefCell`] and
+/// errors are stored rather than returned: on failure [`count`] and [`direction`] repeat the
+/// last value read, and the error can be collected with [`take_error`]. Latched [`Str`] events
+/// seen while reading the direction are kept for [`Ls7366::poll_events`].
+///
+/// [`Qei`]: https://docs.rs/embedded-hal/0.2.7/embedded_hal/trait.Qei.html
+/// [`Ls7366`]: ../struct.Ls7366.html
+/// [`Ls7366::get_count`]: ../struct.Ls7366.html#method.get_count
+/// [`Ls7366::poll_events`]: ../struct.Ls7366.html#method.poll_events
+/// [`CounterMode`]: ../mdr1/enum.CounterMode.html
+/// [`RefCell`]: https://doc.rust-lang.org/core/cell/struct.RefCell.html
+/// [`count`]: #method.count
+/// [`direction`]: #method.direction
+/// [`take_error`]: #method.take_error
+/// [`Str`]: ../ir/enum.Target.html#variant.Str
+pub struct Eh02Qei<SPI: ErrorType> {
+    driver: RefCell<Ls7366<SPI>>,
+    last_count: Cell<i64>,
+    last_direction: Cell<Direction>,
+    error: RefCell<Option<Error<SPI::Error>>>,
+}
+
+impl<SPI, SpiError> Eh02Qei<SPI>
+    where SPI: SpiDevice<u8, Error=SpiError> {
+    pub fn new(driver: Ls7366<SPI>) -> Self {
+        Eh02Qei {
+            driver: RefCell::new(driver),
+            last_count: Cell::new(0),
+            last_direction: Cell::new(Direction::Upcounting),
+            error: RefCell::new(None),
+        }
+    }
+
+    /// Returns the most recent error raised while reading the chip, if any, and forgets it.
+    pub fn take_error(&self) -> Option<Error<SpiError>> {
+        self.error.borrow_mut().take()
+    }
+
+    /// Gives access to the underlying driver.
+    pub fn driver(&mut self) -> &mut Ls7366<SPI> {
+        self.driver.get_mut()
+    }
+
+    /// Hands the driver back.
+    pub fn release(self) -> Ls7366<SPI> {
+        self.driver.into_inner()
+    }
+
+    fn read_direction(&self) -> Result<Direction, Error<SpiError>> {
+        let mut driver = self.driver.borrow_mut();
+        let status = driver.get_status()?;
+        driver.take_from_status(&status, Events::empty())?;
+        Ok(status.count_direction.into())
+    }
+}
+
+impl<SPI, SpiError> Qei for Eh02Qei<SPI>
+    where SPI: SpiDevice<u8, Error=SpiError> {
+    type Count = i64;
+
+    fn count(&self) -> i64 {
+        match self.driver.borrow_mut().get_count() {
+            Ok(count) => self.last_count.set(count),
+            Err(error) => *self.error.borrow_mut() = Some(error),
+        }
+        self.last_count.get()
+    }
+
+    fn direction(&self) -> Direction {
+        match self.read_direction() {
+            Ok(direction) => self.last_direction.set(direction),
+            Err(error) => *self.error.borrow_mut() = Some(error),
+        }
+        self.last_direction.get()
+    }
+}
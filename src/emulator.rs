@@ -0,0 +1,481 @@
+//! Behavioural model of the LS7366, for host-side testing without hardware.
+//!
+//! [`Ls7366Emulator`] decodes instruction register bytes exactly like the chip, maintaining
+//! [`Mdr0`], [`Mdr1`], [`Dtr`], [`Cntr`], [`Otr`] and [`Str`]. Its encoder inputs are driven
+//! through [`Ls7366Emulator::apply`], which counts according to the configured quadrature,
+//! cycle and index modes.
+//!
+//! The emulator sits behind a `RefCell` so a test can keep driving its inputs while an
+//! [`Ls7366`] talks to it through an [`EmulatorDevice`]. Its LFLAG output can be read through an
+//! [`EmulatorFlagPin`].
+//!
+//! ```
+//! use core::cell::RefCell;
+//! use ls7366::Ls7366;
+//! use ls7366::emulator::{EmulatorDevice, Inputs, Ls7366Emulator};
+//!
+//! let chip = RefCell::new(Ls7366Emulator::new());
+//! let mut driver = Ls7366::new(EmulatorDevice::new(&chip)).unwrap();
+//!
+//! // One full quadrature cycle with A leading B counts up by 4 in the default 4x mode.
+//! for (a, b) in [(true, false), (true, true), (false, true), (false, false)].iter() {
+//!     chip.borrow_mut().apply(Inputs { a: *a, b: *b, index: false });
+//! }
+//! assert_eq!(driver.get_count().unwrap(), 4);
+//! ```
+//!
+//! Behaviour follows the datasheet, with these modelling choices:
+//! - Reading [`Str`] clears its latches (carry, borrow, compare, index and power loss).
+//! - Register contents are clocked out MSB first; any further bytes of a read repeat 1-byte
+//!   registers and are zero for the counter registers.
+//! - In non-quadrature mode the counter advances on the rising edge of A, counting up while B
+//!   is high and down while B is low.
+//! - Transitions where A and B change together are invalid and ignored, as is the index filter.
+//!
+//! [`Ls7366Emulator`]: ./struct.Ls7366Emulator.html
+//! [`Ls7366Emulator::apply`]: ./struct.Ls7366Emulator.html#method.apply
+//! [`EmulatorDevice`]: ./struct.EmulatorDevice.html
+//! [`EmulatorFlagPin`]: ./struct.EmulatorFlagPin.html
+//! [`Ls7366`]: ../struct.Ls7366.html
+//! [`Mdr0`]: ../ir/enum.Target.html#variant.Mdr0
+//! [`Mdr1`]: ../ir/enum.Target.html#variant.Mdr1
+//! [`Dtr`]: ../ir/enum.Target.html#variant.Dtr
+//! [`Cntr`]: ../ir/enum.Target.html#variant.Cntr
+//! [`Otr`]: ../ir/enum.Target.html#variant.Otr
+//! [`Str`]: ../ir/enum.Target.html#variant.Str
+
+use core::cell::RefCell;
+use core::convert::Infallible;
+
+use embedded_hal::digital::{self, InputPin};
+use embedded_hal::spi::{ErrorType, Operation, SpiDevice};
+
+use crate::ir::{Action, InstructionRegister, Target};
+use crate::mdr0::{CycleCountMode, IndexMode, Mdr0, QuadCountMode};
+use crate::mdr1::Mdr1;
+use crate::str_register::Str;
+use crate::traits::{Decodable, Encodable};
+
+const STR_SIGN: u8 = 1 << 0;
+const STR_UP: u8 = 1 << 1;
+const STR_POWER_LOSS: u8 = 1 << 2;
+const STR_COUNT_ENABLED: u8 = 1 << 3;
+const STR_INDEX: u8 = 1 << 4;
+const STR_COMPARE: u8 = 1 << 5;
+const STR_BORROW: u8 = 1 << 6;
+const STR_CARRY: u8 = 1 << 7;
+/// Bits cleared by reading [`Str`](../ir/enum.Target.html#variant.Str).
+const STR_LATCHES: u8 = STR_POWER_LOSS | STR_INDEX | STR_COMPARE | STR_BORROW | STR_CARRY;
+
+/// Logic levels of the chip's encoder inputs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Inputs {
+    /// Channel A (clock in non-quadrature mode).
+    pub a: bool,
+    /// Channel B (direction in non-quadrature mode).
+    pub b: bool,
+    /// Index input, before the optional inversion configured in `Mdr0`.
+    pub index: bool,
+}
+
+/// Progress through the SPI transaction currently in flight.
+#[derive(Debug)]
+enum Transaction {
+    /// Waiting for the instruction byte.
+    Idle,
+    /// Instruction received; `bytes` data bytes have been exchanged since.
+    Active { instruction: InstructionRegister, bytes: usize },
+}
+
+/// Software model of an LS7366.
+#[derive(Debug)]
+pub struct Ls7366Emulator {
+    mdr0: Mdr0,
+    mdr1: Mdr1,
+    dtr: u32,
+    cntr: u32,
+    otr: u32,
+    /// Status bits other than counting enabled, which is derived on demand.
+    status: u8,
+    /// Counting stopped by a carry or borrow in single-cycle mode.
+    single_cycle_stopped: bool,
+    inputs: Inputs,
+    transaction: Transaction,
+}
+
+impl Default for Ls7366Emulator {
+    fn default() -> Self {
+        Ls7366Emulator::new()
+    }
+}
+
+impl Ls7366Emulator {
+    /// Creates a freshly powered-on chip: all registers cleared, power loss latched.
+    pub fn new() -> Self {
+        Ls7366Emulator {
+            mdr0: Mdr0::default(),
+            mdr1: Mdr1::default(),
+            dtr: 0,
+            cntr: 0,
+            otr: 0,
+            status: STR_POWER_LOSS,
+            single_cycle_stopped: false,
+            inputs: Inputs::default(),
+            transaction: Transaction::Idle,
+        }
+    }
+
+    /// Simulates the chip losing and regaining power.
+    pub fn power_cycle(&mut self) {
+        let inputs = self.inputs;
+        *self = Ls7366Emulator::new();
+        self.inputs = inputs;
+    }
+
+    /// Drives the encoder inputs to new levels, counting any resulting edges.
+    pub fn apply(&mut self, inputs: Inputs) {
+        let previous = self.inputs;
+        self.inputs = inputs;
+
+        if previous.a != inputs.a || previous.b != inputs.b {
+            self.decode_quadrature(previous, inputs);
+        }
+        let active_low = self.mdr0.is_index_inverted;
+        if previous.index != inputs.index && inputs.index != active_low {
+            self.index_event();
+        }
+    }
+
+    /// Current contents of the counter register.
+    pub fn cntr(&self) -> u32 {
+        self.cntr
+    }
+
+    /// Current contents of the output register.
+    pub fn otr(&self) -> u32 {
+        self.otr
+    }
+
+    /// Current contents of the data transfer register.
+    pub fn dtr(&self) -> u32 {
+        self.dtr
+    }
+
+    /// Current primary configuration.
+    pub fn mdr0(&self) -> Mdr0 {
+        self.mdr0
+    }
+
+    /// Current secondary configuration.
+    pub fn mdr1(&self) -> Mdr1 {
+        self.mdr1
+    }
+
+    /// Current status register, without the read side effects of an SPI read.
+    pub fn status(&self) -> Str {
+        match Str::decode(self.status_byte()) {
+            Ok(status) => status,
+            Err(_) => unreachable!("every status byte decodes"),
+        }
+    }
+
+    /// Current encoder input levels.
+    pub fn inputs(&self) -> Inputs {
+        self.inputs
+    }
+
+    /// Whether the latched flag output (LFLAG) is asserted, i.e. driven low.
+    ///
+    /// It is asserted while any [`Str`] latch enabled for the flag outputs in [`Mdr1`] is set.
+    /// The pulsed DFLAG output is not modelled.
+    ///
+    /// [`Str`]: ../ir/enum.Target.html#variant.Str
+    /// [`Mdr1`]: ../ir/enum.Target.html#variant.Mdr1
+    pub fn lflag(&self) -> bool {
+        let mut enabled = 0;
+        if self.mdr1.flag_on_cy {
+            enabled |= STR_CARRY;
+        }
+        if self.mdr1.flag_on_bw {
+            enabled |= STR_BORROW;
+        }
+        if self.mdr1.flag_on_cmp {
+            enabled |= STR_COMPARE;
+        }
+        if self.mdr1.flag_on_idx {
+            enabled |= STR_INDEX;
+        }
+        self.status & enabled != 0
+    }
+
+    fn status_byte(&self) -> u8 {
+        if self.counting_enabled() {
+            self.status | STR_COUNT_ENABLED
+        } else {
+            self.status
+        }
+    }
+
+    fn counting_enabled(&self) -> bool {
+        !self.mdr1.disable_counting && !self.single_cycle_stopped
+    }
+
+    fn max_value(&self) -> u32 {
+        self.mdr1.counter_mode.max_value()
+    }
+
+    fn decode_quadrature(&mut self, previous: Inputs, current: Inputs) {
+        if let QuadCountMode::NonQuad = self.mdr0.quad_count_mode {
+            if !previous.a && current.a {
+                self.count(current.b);
+            }
+            return;
+        }
+        // Quadrature states in the order they occur while counting up (A leading B).
+        let phase = |inputs: Inputs| match (inputs.a, inputs.b) {
+            (false, false) => 0u8,
+            (true, false) => 1,
+            (true, true) => 2,
+            (false, true) => 3,
+        };
+        let from = phase(previous);
+        let to = phase(current);
+        let up = match (to + 4 - from) % 4 {
+            1 => true,
+            3 => false,
+            // Both channels changed at once, which no valid encoder signal does.
+            _ => return,
+        };
+        // The transition as seen while counting up, i.e. the state being left when going up.
+        let edge = if up { from } else { to };
+        let counts = match self.mdr0.quad_count_mode {
+            QuadCountMode::Quad4x => true,
+            // Edges of A only.
+            QuadCountMode::Quad2x => edge == 0 || edge == 2,
+            // Rising edge of A while B is low.
+            QuadCountMode::Quad1x => edge == 0,
+            QuadCountMode::NonQuad => false,
+        };
+        if counts {
+            self.count(up);
+        }
+    }
+
+    fn count(&mut self, up: bool) {
+        if !self.counting_enabled() {
+            return;
+        }
+        if up {
+            self.status |= STR_UP;
+        } else {
+            self.status &= !STR_UP;
+        }
+
+        let max = self.max_value();
+        let (next, carry, borrow) = match (self.mdr0.cycle_count_mode, up) {
+            (CycleCountMode::RangeLimit, true) if self.cntr == self.dtr => (self.cntr, true, false),
+            (CycleCountMode::RangeLimit, false) if self.cntr == 0 => (self.cntr, false, true),
+            (CycleCountMode::ModuloN, true) if self.cntr == self.dtr => (0, true, false),
+            (CycleCountMode::ModuloN, false) if self.cntr == 0 => (self.dtr, false, true),
+            (_, true) if self.cntr == max => (0, true, false),
+            (_, false) if self.cntr == 0 => (max, false, true),
+            (_, true) => (self.cntr + 1, false, false),
+            (_, false) => (self.cntr - 1, false, false),
+        };
+        self.cntr = next;
+
+        if carry {
+            self.status = (self.status | STR_CARRY) & !STR_SIGN;
+        }
+        if borrow {
+            self.status |= STR_BORROW | STR_SIGN;
+        }
+        if (carry || borrow) && self.mdr0.cycle_count_mode == CycleCountMode::SingleCycle {
+            self.single_cycle_stopped = true;
+        }
+        if self.cntr == self.dtr {
+            self.status |= STR_COMPARE;
+        }
+    }
+
+    fn index_event(&mut self) {
+        match self.mdr0.index_mode {
+            IndexMode::DisableIndex => return,
+            IndexMode::LoadCntr => self.load_cntr(),
+            IndexMode::ClearCntr => self.clear_cntr(),
+            IndexMode::LoadOtr => self.otr = self.cntr,
+        }
+        self.status |= STR_INDEX;
+    }
+
+    fn load_cntr(&mut self) {
+        self.cntr = self.dtr & self.max_value();
+        self.single_cycle_stopped = false;
+    }
+
+    fn clear_cntr(&mut self) {
+        self.cntr = 0;
+        self.single_cycle_stopped = false;
+    }
+
+    /// Executes the immediate part of an instruction, right after its byte was received.
+    fn begin(&mut self, instruction: &InstructionRegister) {
+        match (&instruction.action, &instruction.target) {
+            (Action::Clear, Target::Mdr0) => self.mdr0 = Mdr0::default(),
+            (Action::Clear, Target::Mdr1) => self.mdr1 = Mdr1::default(),
+            (Action::Clear, Target::Cntr) => self.clear_cntr(),
+            (Action::Clear, Target::Otr) => self.otr = 0,
+            (Action::Clear, Target::Str) => self.status = 0,
+            (Action::Load, Target::Cntr) => self.load_cntr(),
+            (Action::Load, Target::Otr) => self.otr = self.cntr,
+            // Reading CNTR goes through OTR.
+            (Action::Read, Target::Cntr) => self.otr = self.cntr,
+            (Action::Write, Target::Dtr) => self.dtr = 0,
+            _ => {}
+        }
+    }
+
+    /// Exchanges one data byte of an instruction, returning the byte clocked out on MISO.
+    fn data(&mut self, instruction: &InstructionRegister, index: usize, mosi: u8) -> u8 {
+        let width = self.mdr1.counter_mode.byte_count();
+        let register_byte = |value: u32| {
+            if index < width {
+                (value >> (8 * (width - 1 - index))) as u8
+            } else {
+                0x00
+            }
+        };
+        match (&instruction.action, &instruction.target) {
+            (Action::Read, Target::Mdr0) => self.mdr0.encode(),
+            (Action::Read, Target::Mdr1) => self.mdr1.encode(),
+            (Action::Read, Target::Str) => self.status_byte(),
+            (Action::Read, Target::Cntr) | (Action::Read, Target::Otr) => register_byte(self.otr),
+            (Action::Write, Target::Mdr0) if index == 0 => {
+                if let Ok(mdr0) = Mdr0::decode(mosi) {
+                    self.mdr0 = mdr0;
+                }
+                0x00
+            }
+            (Action::Write, Target::Mdr1) if index == 0 => {
+                if let Ok(mdr1) = Mdr1::decode(mosi) {
+                    self.mdr1 = mdr1;
+                    let max = self.max_value();
+                    self.cntr &= max;
+                    self.otr &= max;
+                }
+                0x00
+            }
+            (Action::Write, Target::Dtr) if index < width => {
+                self.dtr = ((self.dtr << 8) | mosi as u32) & self.max_value();
+                0x00
+            }
+            _ => 0x00,
+        }
+    }
+
+    fn exchange(&mut self, mosi: u8) -> u8 {
+        match core::mem::replace(&mut self.transaction, Transaction::Idle) {
+            Transaction::Idle => {
+                if let Ok(instruction) = InstructionRegister::decode(mosi) {
+                    self.begin(&instruction);
+                    self.transaction = Transaction::Active { instruction, bytes: 0 };
+                }
+                0x00
+            }
+            Transaction::Active { instruction, bytes } => {
+                let miso = self.data(&instruction, bytes, mosi);
+                self.transaction = Transaction::Active { instruction, bytes: bytes + 1 };
+                miso
+            }
+        }
+    }
+
+    /// Ends the transaction in flight, as the chip does when its select line is released.
+    fn end(&mut self) {
+        if let Transaction::Active { instruction: InstructionRegister { action: Action::Read, target: Target::Str }, .. } = self.transaction {
+            self.status &= !STR_LATCHES;
+        }
+        self.transaction = Transaction::Idle;
+    }
+
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) {
+        for operation in operations.iter_mut() {
+            match operation {
+                Operation::Read(words) => {
+                    for word in words.iter_mut() {
+                        *word = self.exchange(0x00);
+                    }
+                }
+                Operation::Write(words) => {
+                    for word in words.iter() {
+                        self.exchange(*word);
+                    }
+                }
+                Operation::Transfer(read, write) => {
+                    for i in 0..read.len().max(write.len()) {
+                        let miso = self.exchange(write.get(i).copied().unwrap_or(0x00));
+                        if let Some(word) = read.get_mut(i) {
+                            *word = miso;
+                        }
+                    }
+                }
+                Operation::TransferInPlace(words) => {
+                    for word in words.iter_mut() {
+                        *word = self.exchange(*word);
+                    }
+                }
+                Operation::DelayNs(_) => {}
+            }
+        }
+        self.end();
+    }
+}
+
+/// SPI device talking to an [`Ls7366Emulator`] shared through a `RefCell`.
+///
+/// [`Ls7366Emulator`]: ./struct.Ls7366Emulator.html
+pub struct EmulatorDevice<'a> {
+    chip: &'a RefCell<Ls7366Emulator>,
+}
+
+impl<'a> EmulatorDevice<'a> {
+    pub fn new(chip: &'a RefCell<Ls7366Emulator>) -> Self {
+        EmulatorDevice { chip }
+    }
+}
+
+/// The emulated chip's LFLAG output, an active low input pin from the host's point of view.
+pub struct EmulatorFlagPin<'a> {
+    chip: &'a RefCell<Ls7366Emulator>,
+}
+
+impl<'a> EmulatorFlagPin<'a> {
+    pub fn new(chip: &'a RefCell<Ls7366Emulator>) -> Self {
+        EmulatorFlagPin { chip }
+    }
+}
+
+impl<'a> digital::ErrorType for EmulatorFlagPin<'a> {
+    type Error = Infallible;
+}
+
+impl<'a> InputPin for EmulatorFlagPin<'a> {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.chip.borrow().lflag())
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.chip.borrow().lflag())
+    }
+}
+
+impl<'a> ErrorType for EmulatorDevice<'a> {
+    type Error = Infallible;
+}
+
+impl<'a> SpiDevice<u8> for EmulatorDevice<'a> {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+        self.chip.borrow_mut().transaction(operations);
+        Ok(())
+    }
+}
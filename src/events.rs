@@ -0,0 +1,153 @@
+//! Latched chip events as a set of flags.
+//!
+//! The latches of the [`Str`] register (carry, borrow, compare, index and power loss) record
+//! that something happened since they were last cleared. [`Ls7366::poll_events`] collects them
+//! into an [`Events`] set and clears them, so every occurrence is reported by exactly one poll.
+//! The driver adds events of its own, such as recovering from a power loss.
+//!
+//! ```
+//! use ls7366::events::Events;
+//!
+//! let events = Events::CARRY | Events::INDEX;
+//! assert!(events.contains(Events::INDEX));
+//! assert!(!events.intersects(Events::BORROW | Events::COMPARE));
+//! assert_eq!(events.iter().count(), 2);
+//! ```
+//!
+//! [`Str`]: ../str_register/struct.Str.html
+//! [`Ls7366::poll_events`]: ../struct.Ls7366.html#method.poll_events
+//! [`Events`]: ./struct.Events.html
+
+use core::ops::{BitAnd, BitOr, BitOrAssign, Not};
+
+use crate::str_register::Str;
+
+/// A set of chip events.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Events(u16);
+
+impl Events {
+    /// The counter overflowed.
+    pub const CARRY: Events = Events(1 << 0);
+    /// The counter underflowed.
+    pub const BORROW: Events = Events(1 << 1);
+    /// The counter matched the [`Dtr`](../ir/enum.Target.html#variant.Dtr).
+    pub const COMPARE: Events = Events(1 << 2);
+    /// An index pulse was seen.
+    pub const INDEX: Events = Events(1 << 3);
+    /// The chip lost power.
+    pub const POWER_LOSS: Events = Events(1 << 4);
+    /// The driver restored the chip's configuration after a power loss.
+    pub const POWER_LOSS_RECOVERED: Events = Events(1 << 5);
+
+    const ALL: [Events; 6] = [
+        Events::CARRY,
+        Events::BORROW,
+        Events::COMPARE,
+        Events::INDEX,
+        Events::POWER_LOSS,
+        Events::POWER_LOSS_RECOVERED,
+    ];
+
+    pub const fn empty() -> Self {
+        Events(0)
+    }
+
+    pub fn all() -> Self {
+        Events::ALL.iter().fold(Events::empty(), |all, event| all | *event)
+    }
+
+    /// The raw flag bits.
+    pub const fn bits(&self) -> u16 {
+        self.0
+    }
+
+    /// Rebuilds a set from [`bits`](#method.bits), dropping unknown bits.
+    pub fn from_bits_truncate(bits: u16) -> Self {
+        Events(bits) & Events::all()
+    }
+
+    /// The events latched in a status register reading.
+    pub fn from_status(status: &Str) -> Self {
+        let mut events = Events::empty();
+        events.set(Events::CARRY, status.cary);
+        events.set(Events::BORROW, status.borrow);
+        events.set(Events::COMPARE, status.compare);
+        events.set(Events::INDEX, status.index);
+        events.set(Events::POWER_LOSS, status.power_loss);
+        events
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether every event in `other` is in this set.
+    pub const fn contains(&self, other: Events) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Whether any event in `other` is in this set.
+    pub const fn intersects(&self, other: Events) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    pub fn insert(&mut self, other: Events) {
+        self.0 |= other.0;
+    }
+
+    pub fn remove(&mut self, other: Events) {
+        self.0 &= !other.0;
+    }
+
+    /// Inserts `other` if `value` is true, removes it otherwise.
+    pub fn set(&mut self, other: Events, value: bool) {
+        if value {
+            self.insert(other);
+        } else {
+            self.remove(other);
+        }
+    }
+
+    /// Iterates over the single events in this set.
+    pub fn iter(&self) -> impl Iterator<Item = Events> {
+        let events = *self;
+        Events::ALL.iter().copied().filter(move |event| events.contains(*event))
+    }
+}
+
+impl From<Str> for Events {
+    fn from(status: Str) -> Self {
+        Events::from_status(&status)
+    }
+}
+
+impl BitOr for Events {
+    type Output = Events;
+
+    fn bitor(self, other: Events) -> Events {
+        Events(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for Events {
+    fn bitor_assign(&mut self, other: Events) {
+        self.insert(other);
+    }
+}
+
+impl BitAnd for Events {
+    type Output = Events;
+
+    fn bitand(self, other: Events) -> Events {
+        Events(self.0 & other.0)
+    }
+}
+
+impl Not for Events {
+    type Output = Events;
+
+    fn not(self) -> Events {
+        Events::from_bits_truncate(!self.0)
+    }
+}
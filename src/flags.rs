@@ -0,0 +1,195 @@
+//! Interrupt-driven event handling through the chip's flag outputs.
+//!
+//! The chip can assert its LFLAG and DFLAG outputs on the events enabled in [`Mdr1`]: LFLAG
+//! stays low until [`Str`] is cleared, DFLAG pulses low as each event happens. Wiring either
+//! one to an interrupt-capable input lets firmware react to compare matches or index pulses
+//! without polling.
+//!
+//! A [`FlagHandler`] runs in the interrupt handler: it reads and clears [`Str`], which also
+//! releases LFLAG, and pushes the decoded [`Events`] into an [`EventQueue`]. The queue is
+//! lock-free and only uses atomic loads and stores, so it can be shared between an interrupt
+//! and the main loop, even on cores without compare-and-swap.
+//!
+//! An LFLAG interrupt can use [`FlagHandler::handle_pin`], which skips the chip when the pin
+//! shows nothing is latched. DFLAG is usually back high by the time the interrupt runs, so a
+//! DFLAG interrupt must call [`FlagHandler::handle`] instead.
+//!
+//! ```
+//! use core::cell::RefCell;
+//! use ls7366::Ls7366;
+//! use ls7366::emulator::{EmulatorDevice, EmulatorFlagPin, Ls7366Emulator};
+//! use ls7366::events::Events;
+//! use ls7366::flags::{EventQueue, FlagHandler};
+//!
+//! static EVENTS: EventQueue<8> = EventQueue::new();
+//!
+//! let chip = RefCell::new(Ls7366Emulator::new());
+//! let mut lflag = EmulatorFlagPin::new(&chip);
+//! let driver = Ls7366::new(EmulatorDevice::new(&chip)).unwrap();
+//! let mut handler = FlagHandler::new(driver, &EVENTS, Events::INDEX).unwrap();
+//!
+//! // In the interrupt handler for the LFLAG pin:
+//! handler.handle_pin(&mut lflag).unwrap();
+//!
+//! // In the main loop:
+//! while let Some(events) = EVENTS.pop() {
+//!     if events.contains(Events::INDEX) {
+//!         // ...
+//!     }
+//! }
+//! ```
+//!
+//! [`Mdr1`]: ../mdr1/struct.Mdr1.html
+//! [`Str`]: ../ir/enum.Target.html#variant.Str
+//! [`FlagHandler`]: ./struct.FlagHandler.html
+//! [`FlagHandler::handle_pin`]: ./struct.FlagHandler.html#method.handle_pin
+//! [`FlagHandler::handle`]: ./struct.FlagHandler.html#method.handle
+//! [`Events`]: ../events/struct.Events.html
+//! [`EventQueue`]: ./struct.EventQueue.html
+
+use core::convert::Infallible;
+use core::sync::atomic::{AtomicU16, AtomicUsize, Ordering};
+
+use embedded_hal::digital::InputPin;
+use embedded_hal::spi::SpiDevice;
+
+use crate::events::Events;
+use crate::{Error, Ls7366};
+
+#[derive(Clone, Debug)]
+pub enum FlagError<SpiError, PinError> {
+    /// Talking to the chip failed.
+    Driver(Error<SpiError>),
+    /// Reading the flag pin failed.
+    Pin(PinError),
+    /// The queue had no room for these events, which are dropped.
+    QueueFull(Events),
+}
+
+impl<SpiError, PinError> From<Error<SpiError>> for FlagError<SpiError, PinError> {
+    fn from(error: Error<SpiError>) -> Self {
+        FlagError::Driver(error)
+    }
+}
+
+/// Single-producer single-consumer queue of [`Events`], holding up to `N` entries.
+///
+/// Pushing from more than one context at a time, or popping from more than one, can lose or
+/// repeat entries; it is memory safe regardless.
+///
+/// [`Events`]: ../events/struct.Events.html
+pub struct EventQueue<const N: usize> {
+    slots: [AtomicU16; N],
+    /// Entries ever pushed, wrapping.
+    tail: AtomicUsize,
+    /// Entries ever popped, wrapping.
+    head: AtomicUsize,
+}
+
+impl<const N: usize> Default for EventQueue<N> {
+    fn default() -> Self {
+        EventQueue::new()
+    }
+}
+
+impl<const N: usize> EventQueue<N> {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const EMPTY: AtomicU16 = AtomicU16::new(0);
+
+    pub const fn new() -> Self {
+        EventQueue {
+            slots: [Self::EMPTY; N],
+            tail: AtomicUsize::new(0),
+            head: AtomicUsize::new(0),
+        }
+    }
+
+    /// Appends `events`, handing them back if the queue is full.
+    pub fn push(&self, events: Events) -> Result<(), Events> {
+        let tail = self.tail.load(Ordering::Relaxed);
+        if tail.wrapping_sub(self.head.load(Ordering::Acquire)) >= N {
+            return Err(events);
+        }
+        self.slots[tail % N].store(events.bits(), Ordering::Relaxed);
+        self.tail.store(tail.wrapping_add(1), Ordering::Release);
+        Ok(())
+    }
+
+    /// Removes the oldest entry.
+    pub fn pop(&self) -> Option<Events> {
+        let head = self.head.load(Ordering::Relaxed);
+        if head == self.tail.load(Ordering::Acquire) {
+            return None;
+        }
+        let events = Events::from_bits_truncate(self.slots[head % N].load(Ordering::Relaxed));
+        self.head.store(head.wrapping_add(1), Ordering::Release);
+        Some(events)
+    }
+
+    /// Number of queued entries.
+    pub fn len(&self) -> usize {
+        self.tail.load(Ordering::Acquire).wrapping_sub(self.head.load(Ordering::Acquire))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Interrupt-side handler feeding chip events into an [`EventQueue`].
+///
+/// [`EventQueue`]: ./struct.EventQueue.html
+pub struct FlagHandler<'q, SPI, const N: usize> {
+    driver: Ls7366<SPI>,
+    queue: &'q EventQueue<N>,
+}
+
+impl<'q, SPI, SpiError, const N: usize> FlagHandler<'q, SPI, N>
+    where SPI: SpiDevice<u8, Error=SpiError> {
+    /// Routes `events` to the chip's flag outputs and clears any events latched so far.
+    pub fn new(mut driver: Ls7366<SPI>, queue: &'q EventQueue<N>, events: Events) -> Result<Self, Error<SpiError>> {
+        driver.set_flag_events(events)?;
+        driver.clear_status()?;
+        Ok(FlagHandler { driver, queue })
+    }
+
+    /// Reads and clears the chip's events and queues them, returning what was found.
+    ///
+    /// Call this from the flag interrupt. Nothing is queued when no event is latched, e.g.
+    /// for a spurious interrupt.
+    pub fn handle(&mut self) -> Result<Events, FlagError<SpiError, Infallible>> {
+        let events = self.driver.poll_events()?;
+        if !events.is_empty() {
+            self.queue.push(events).map_err(FlagError::QueueFull)?;
+        }
+        Ok(events)
+    }
+
+    /// Like [`handle`], but only talks to the chip if the active low `flag` pin is asserted.
+    ///
+    /// `flag` must be LFLAG, which stays low until the events are collected. A DFLAG pulse has
+    /// usually ended before this runs, so its events would be left behind; call [`handle`] from
+    /// a DFLAG interrupt instead.
+    ///
+    /// [`handle`]: #method.handle
+    pub fn handle_pin<PIN: InputPin>(&mut self, flag: &mut PIN) -> Result<Events, FlagError<SpiError, PIN::Error>> {
+        if !flag.is_low().map_err(FlagError::Pin)? {
+            return Ok(Events::empty());
+        }
+        self.handle().map_err(|error| match error {
+            FlagError::Driver(error) => FlagError::Driver(error),
+            FlagError::QueueFull(events) => FlagError::QueueFull(events),
+            FlagError::Pin(never) => match never {},
+        })
+    }
+
+    /// Gives access to the underlying driver.
+    pub fn driver(&mut self) -> &mut Ls7366<SPI> {
+        &mut self.driver
+    }
+
+    /// Hands the driver back, leaving the flag outputs configured.
+    pub fn release(self) -> Ls7366<SPI> {
+        self.driver
+    }
+}
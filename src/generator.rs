@@ -0,0 +1,362 @@
+//! Encoder signal generation for driving the [`emulator`] from motion profiles.
+//!
+//! Motion profiles are iterators yielding the number of steps moved during each tick, so they
+//! compose with the standard iterator adapters: chaining a forward and a backward
+//! [`ConstantVelocity`] is a direction reversal. A [`QuadratureGenerator`] turns those steps into
+//! the [`Inputs`] sequence an encoder would produce, optionally with an index pulse once per
+//! revolution and with glitches injected on the inputs.
+//!
+//! ```
+//! use core::cell::RefCell;
+//! use ls7366::Ls7366;
+//! use ls7366::emulator::{EmulatorDevice, Ls7366Emulator};
+//! use ls7366::generator::{QuadratureGenerator, Signalling, Trapezoidal};
+//! use ls7366::mdr0::QuadCountMode;
+//!
+//! let chip = RefCell::new(Ls7366Emulator::new());
+//! let mut driver = Ls7366::new(EmulatorDevice::new(&chip)).unwrap();
+//!
+//! let mut generator = QuadratureGenerator::new(Signalling::Quadrature);
+//! for inputs in generator.run(Trapezoidal::new(-1000, 25, 3)) {
+//!     chip.borrow_mut().apply(inputs);
+//! }
+//! let expected = generator.expected_count(QuadCountMode::Quad4x).unwrap();
+//! assert_eq!(expected, -1000);
+//! assert_eq!(driver.get_count().unwrap(), expected);
+//! ```
+//!
+//! [`emulator`]: ../emulator/index.html
+//! [`Inputs`]: ../emulator/struct.Inputs.html
+//! [`ConstantVelocity`]: ./struct.ConstantVelocity.html
+//! [`QuadratureGenerator`]: ./struct.QuadratureGenerator.html
+
+use crate::emulator::Inputs;
+use crate::mdr0::QuadCountMode;
+
+/// How steps are encoded on the A and B inputs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Signalling {
+    /// A and B in quadrature, A leading B when moving forward. One step is one edge.
+    Quadrature,
+    /// A carries one clock pulse per step, B is high when moving forward.
+    ClockDirection,
+}
+
+/// Converts motion into encoder input levels.
+///
+/// The generator starts with all inputs low at position zero, matching a freshly created
+/// [`Ls7366Emulator`].
+///
+/// [`Ls7366Emulator`]: ../emulator/struct.Ls7366Emulator.html
+#[derive(Clone, Debug)]
+pub struct QuadratureGenerator {
+    signalling: Signalling,
+    /// Steps moved since creation.
+    position: i64,
+    /// Steps per revolution, for the index pulse.
+    steps_per_revolution: Option<u32>,
+    /// Steps between injected glitches.
+    glitch_interval: Option<u32>,
+    steps_since_glitch: u32,
+    inputs: Inputs,
+}
+
+impl QuadratureGenerator {
+    pub fn new(signalling: Signalling) -> Self {
+        QuadratureGenerator {
+            signalling,
+            position: 0,
+            steps_per_revolution: None,
+            glitch_interval: None,
+            steps_since_glitch: 0,
+            inputs: Inputs::default(),
+        }
+    }
+
+    /// Raises the index input once every `steps` steps, for the duration of one step.
+    pub fn with_index(mut self, steps: u32) -> Self {
+        self.steps_per_revolution = Some(steps);
+        self
+    }
+
+    /// Injects a glitch after every `steps` steps.
+    ///
+    /// A glitch briefly toggles one input and restores it. In quadrature signalling this is a
+    /// step forth and back on A; with clock and direction signalling it toggles B while A is
+    /// low. Either way a correct decoder ends up with no net count.
+    pub fn with_glitches(mut self, steps: u32) -> Self {
+        self.glitch_interval = Some(steps);
+        self
+    }
+
+    /// Steps moved since creation.
+    pub fn position(&self) -> i64 {
+        self.position
+    }
+
+    /// Current input levels.
+    pub fn inputs(&self) -> Inputs {
+        self.inputs
+    }
+
+    /// Count a counter fed by this generator should hold, if it started at zero and never
+    /// wrapped.
+    ///
+    /// Returns `None` when `mode` does not decode this generator's signalling: the quadrature
+    /// modes expect [`Quadrature`] signals and [`NonQuad`] expects [`ClockDirection`] signals.
+    ///
+    /// [`Quadrature`]: ./enum.Signalling.html#variant.Quadrature
+    /// [`ClockDirection`]: ./enum.Signalling.html#variant.ClockDirection
+    /// [`NonQuad`]: ../mdr0/enum.QuadCountMode.html#variant.NonQuad
+    pub fn expected_count(&self, mode: QuadCountMode) -> Option<i64> {
+        let position = self.position;
+        match (self.signalling, mode) {
+            (Signalling::ClockDirection, QuadCountMode::NonQuad) => Some(position),
+            (Signalling::Quadrature, QuadCountMode::Quad4x) => Some(position),
+            // Counted on both edges of A, the first and third edge of every cycle.
+            (Signalling::Quadrature, QuadCountMode::Quad2x) => Some((position + 1).div_euclid(2)),
+            // Counted on the rising edge of A, the first edge of every cycle.
+            (Signalling::Quadrature, QuadCountMode::Quad1x) => Some((position + 3).div_euclid(4)),
+            _ => None,
+        }
+    }
+
+    /// Moves one step forward or backward, returning the resulting input levels in order.
+    pub fn step(&mut self, forward: bool) -> Edges {
+        let mut edges = Edges::default();
+        self.position += if forward { 1 } else { -1 };
+        let index = match self.steps_per_revolution {
+            Some(steps) => self.position.rem_euclid(steps as i64) == 0,
+            None => false,
+        };
+        match self.signalling {
+            Signalling::Quadrature => {
+                let (a, b) = match self.position.rem_euclid(4) {
+                    0 => (false, false),
+                    1 => (true, false),
+                    2 => (true, true),
+                    _ => (false, true),
+                };
+                edges.push(&mut self.inputs, Inputs { a, b, index });
+            }
+            Signalling::ClockDirection => {
+                let previous = self.inputs;
+                edges.push(&mut self.inputs, Inputs { a: false, b: forward, ..previous });
+                edges.push(&mut self.inputs, Inputs { a: true, b: forward, index });
+                edges.push(&mut self.inputs, Inputs { a: false, b: forward, index });
+            }
+        }
+
+        if let Some(interval) = self.glitch_interval {
+            self.steps_since_glitch += 1;
+            if self.steps_since_glitch >= interval {
+                self.steps_since_glitch = 0;
+                let restore = self.inputs;
+                let glitch = match self.signalling {
+                    Signalling::Quadrature => Inputs { a: !restore.a, ..restore },
+                    Signalling::ClockDirection => Inputs { b: !restore.b, ..restore },
+                };
+                edges.push(&mut self.inputs, glitch);
+                edges.push(&mut self.inputs, restore);
+            }
+        }
+        edges
+    }
+
+    /// Follows a motion profile, yielding every input change it causes.
+    pub fn run<P: Iterator<Item = i64>>(&mut self, profile: P) -> Run<'_, P> {
+        Run { generator: self, profile, remaining: 0, edges: Edges::default() }
+    }
+}
+
+/// Input levels produced by a single step, at most five.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Edges {
+    levels: [Inputs; 5],
+    len: usize,
+    next: usize,
+}
+
+impl Edges {
+    /// Appends `inputs` if it differs from the current levels.
+    fn push(&mut self, current: &mut Inputs, inputs: Inputs) {
+        if *current != inputs {
+            self.levels[self.len] = inputs;
+            self.len += 1;
+            *current = inputs;
+        }
+    }
+}
+
+impl Iterator for Edges {
+    type Item = Inputs;
+
+    fn next(&mut self) -> Option<Inputs> {
+        if self.next < self.len {
+            self.next += 1;
+            Some(self.levels[self.next - 1])
+        } else {
+            None
+        }
+    }
+}
+
+/// Iterator over the input levels produced by following a motion profile.
+pub struct Run<'a, P> {
+    generator: &'a mut QuadratureGenerator,
+    profile: P,
+    /// Steps left in the current tick.
+    remaining: i64,
+    edges: Edges,
+}
+
+impl<'a, P: Iterator<Item = i64>> Iterator for Run<'a, P> {
+    type Item = Inputs;
+
+    fn next(&mut self) -> Option<Inputs> {
+        loop {
+            if let Some(inputs) = self.edges.next() {
+                return Some(inputs);
+            }
+            while self.remaining == 0 {
+                self.remaining = self.profile.next()?;
+            }
+            let forward = self.remaining > 0;
+            self.remaining -= self.remaining.signum();
+            self.edges = self.generator.step(forward);
+        }
+    }
+}
+
+/// Moves at a constant number of steps per tick, negative velocities moving backwards.
+#[derive(Clone, Debug)]
+pub struct ConstantVelocity {
+    velocity: i64,
+    ticks: u32,
+}
+
+impl ConstantVelocity {
+    pub fn new(velocity: i64, ticks: u32) -> Self {
+        ConstantVelocity { velocity, ticks }
+    }
+}
+
+impl Iterator for ConstantVelocity {
+    type Item = i64;
+
+    fn next(&mut self) -> Option<i64> {
+        if self.ticks == 0 {
+            return None;
+        }
+        self.ticks -= 1;
+        Some(self.velocity)
+    }
+}
+
+/// Point-to-point move: accelerates, cruises at a maximum velocity and decelerates to stop
+/// exactly `distance` steps away.
+#[derive(Clone, Debug)]
+pub struct Trapezoidal {
+    direction: i64,
+    distance: u64,
+    travelled: u64,
+    velocity: u64,
+    max_velocity: u64,
+    acceleration: u64,
+}
+
+impl Trapezoidal {
+    /// Velocities are in steps per tick, the acceleration in steps per tick per tick.
+    /// Both are treated as at least 1.
+    pub fn new(distance: i64, max_velocity: u32, acceleration: u32) -> Self {
+        Trapezoidal {
+            direction: distance.signum(),
+            distance: distance.unsigned_abs(),
+            travelled: 0,
+            velocity: 0,
+            max_velocity: max_velocity.max(1) as u64,
+            acceleration: acceleration.max(1) as u64,
+        }
+    }
+
+    /// Distance covered while decelerating from `velocity` to a stop.
+    fn braking_distance(&self, velocity: u64) -> u64 {
+        let ticks = velocity / self.acceleration;
+        ticks * velocity - self.acceleration * ticks * (ticks + 1) / 2
+    }
+
+    fn fits(&self, velocity: u64) -> bool {
+        self.travelled + velocity + self.braking_distance(velocity) <= self.distance
+    }
+}
+
+impl Iterator for Trapezoidal {
+    type Item = i64;
+
+    fn next(&mut self) -> Option<i64> {
+        let remaining = self.distance - self.travelled;
+        if remaining == 0 {
+            return None;
+        }
+        let accelerated = (self.velocity + self.acceleration).min(self.max_velocity);
+        self.velocity = if self.fits(accelerated) {
+            accelerated
+        } else if self.fits(self.velocity) {
+            self.velocity
+        } else {
+            self.velocity.saturating_sub(self.acceleration).max(1)
+        }
+        .min(remaining);
+        self.travelled += self.velocity;
+        Some(self.direction * self.velocity as i64)
+    }
+}
+
+/// Adds bounded pseudo-random position noise to another profile.
+///
+/// The noise never accumulates: the position stays within `amplitude` steps of the wrapped
+/// profile's, and the final tick returns exactly to it. The sequence is deterministic for a
+/// given seed.
+#[derive(Clone, Debug)]
+pub struct Jitter<P> {
+    profile: P,
+    amplitude: u32,
+    state: u32,
+    offset: i64,
+}
+
+impl<P> Jitter<P> {
+    pub fn new(profile: P, amplitude: u32, seed: u32) -> Self {
+        Jitter { profile, amplitude, state: seed.max(1), offset: 0 }
+    }
+
+    /// Xorshift32.
+    fn random(&mut self) -> u32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        self.state
+    }
+}
+
+impl<P: Iterator<Item = i64>> Iterator for Jitter<P> {
+    type Item = i64;
+
+    fn next(&mut self) -> Option<i64> {
+        match self.profile.next() {
+            Some(steps) => {
+                let span = 2 * self.amplitude as u64 + 1;
+                let offset = (self.random() as u64 % span) as i64 - self.amplitude as i64;
+                let delta = steps + offset - self.offset;
+                self.offset = offset;
+                Some(delta)
+            }
+            None if self.offset != 0 => {
+                let delta = -self.offset;
+                self.offset = 0;
+                Some(delta)
+            }
+            None => None,
+        }
+    }
+}
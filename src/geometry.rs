@@ -0,0 +1,148 @@
+//! Conversion between counts and physical units.
+//!
+//! [`EncoderGeometry`] describes the mechanics behind the counter: the encoder's lines per
+//! revolution, an optional gearbox between the encoder and the output shaft, and an optional
+//! lead screw turning output revolutions into linear travel. Combined with the active
+//! [`QuadCountMode`] it yields a [`Scale`], which converts counts into revolutions, radians,
+//! degrees or millimetres of the output, and target positions back into counts, e.g. for
+//! [`Dtr`] compare values.
+//!
+//! ```
+//! use core::cell::RefCell;
+//! use ls7366::Ls7366;
+//! use ls7366::emulator::{EmulatorDevice, Ls7366Emulator};
+//! use ls7366::geometry::EncoderGeometry;
+//!
+//! let chip = RefCell::new(Ls7366Emulator::new());
+//! let driver = Ls7366::new(EmulatorDevice::new(&chip)).unwrap();
+//!
+//! // 500 line encoder on a motor with a 10:1 gearbox, driving a 5mm lead screw.
+//! let geometry = EncoderGeometry::new(500).with_gear_ratio(10.0).with_lead(5.0);
+//! // The driver counts in 4x mode: 20000 counts per output revolution.
+//! let scale = geometry.scale_for(&driver);
+//! assert_eq!(scale.revolutions(10_000), 0.5);
+//! assert_eq!(scale.degrees(-5_000), -90.0);
+//! assert_eq!(scale.millimetres(40_000), Some(10.0));
+//! assert_eq!(scale.counts_from_millimetres(-1.25), Some(-5_000));
+//! ```
+//!
+//! [`EncoderGeometry`]: ./struct.EncoderGeometry.html
+//! [`Scale`]: ./struct.Scale.html
+//! [`QuadCountMode`]: ../mdr0/enum.QuadCountMode.html
+//! [`Dtr`]: ../ir/enum.Target.html#variant.Dtr
+
+use core::f64::consts::PI;
+
+use embedded_hal::spi::SpiDevice;
+
+use crate::mdr0::QuadCountMode;
+use crate::Ls7366;
+
+/// Mechanical description of an encoder installation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EncoderGeometry {
+    /// Encoder lines (quadrature cycles) per encoder revolution.
+    lines_per_revolution: u32,
+    /// Encoder revolutions per output revolution.
+    gear_ratio: f64,
+    /// Linear travel per output revolution, in millimetres.
+    lead: Option<f64>,
+}
+
+impl EncoderGeometry {
+    /// An encoder with `lines_per_revolution` lines mounted directly on the output.
+    pub fn new(lines_per_revolution: u32) -> Self {
+        EncoderGeometry {
+            lines_per_revolution,
+            gear_ratio: 1.0,
+            lead: None,
+        }
+    }
+
+    /// Sets how many times the encoder turns per revolution of the output, e.g. 10.0 for an
+    /// encoder on the motor side of a 10:1 reduction.
+    pub fn with_gear_ratio(mut self, gear_ratio: f64) -> Self {
+        self.gear_ratio = gear_ratio;
+        self
+    }
+
+    /// Sets the lead screw pitch, in millimetres of travel per output revolution.
+    pub fn with_lead(mut self, lead: f64) -> Self {
+        self.lead = Some(lead);
+        self
+    }
+
+    /// Scale for a counter in `quad_count_mode`.
+    pub fn scale(&self, quad_count_mode: QuadCountMode) -> Scale {
+        Scale {
+            counts_per_revolution: self.lines_per_revolution as f64
+                * quad_count_mode.multiplier() as f64
+                * self.gear_ratio,
+            lead: self.lead,
+        }
+    }
+
+    /// Scale for the quadrature mode the driver has configured.
+    pub fn scale_for<SPI: SpiDevice<u8>>(&self, driver: &Ls7366<SPI>) -> Scale {
+        self.scale(driver.mdr0().quad_count_mode)
+    }
+}
+
+/// Converts counts of a particular counter configuration into output units and back.
+///
+/// Conversions into counts round to the nearest count.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Scale {
+    counts_per_revolution: f64,
+    lead: Option<f64>,
+}
+
+impl Scale {
+    /// Counts per output revolution.
+    pub fn counts_per_revolution(&self) -> f64 {
+        self.counts_per_revolution
+    }
+
+    pub fn revolutions(&self, counts: i64) -> f64 {
+        counts as f64 / self.counts_per_revolution
+    }
+
+    pub fn radians(&self, counts: i64) -> f64 {
+        self.revolutions(counts) * 2.0 * PI
+    }
+
+    pub fn degrees(&self, counts: i64) -> f64 {
+        self.revolutions(counts) * 360.0
+    }
+
+    /// Linear travel, or `None` without a lead screw.
+    pub fn millimetres(&self, counts: i64) -> Option<f64> {
+        self.lead.map(|lead| self.revolutions(counts) * lead)
+    }
+
+    pub fn counts_from_revolutions(&self, revolutions: f64) -> i64 {
+        round(revolutions * self.counts_per_revolution)
+    }
+
+    pub fn counts_from_radians(&self, radians: f64) -> i64 {
+        self.counts_from_revolutions(radians / (2.0 * PI))
+    }
+
+    pub fn counts_from_degrees(&self, degrees: f64) -> i64 {
+        self.counts_from_revolutions(degrees / 360.0)
+    }
+
+    /// Counts for a linear travel, or `None` without a lead screw.
+    pub fn counts_from_millimetres(&self, millimetres: f64) -> Option<i64> {
+        self.lead.map(|lead| self.counts_from_revolutions(millimetres / lead))
+    }
+}
+
+/// Rounds half away from zero, as `f64::round` is not available without `std`.
+fn round(value: f64) -> i64 {
+    if value < 0.0 {
+        (value - 0.5) as i64
+    } else {
+        (value + 0.5) as i64
+    }
+}
@@ -0,0 +1,183 @@
+//! Homing on the encoder's index pulse.
+//!
+//! [`Homing`] temporarily configures the index input to clear the counter, or to load it with
+//! an offset, then keeps calling a caller-supplied motion callback until the index latch shows
+//! the pulse has been seen. The callback moves the axis by whatever means the application has,
+//! which keeps the routine independent of the motion hardware. Afterwards the run-mode
+//! configuration is restored, also when the search fails.
+//!
+//! The index can be detected by polling [`Str`], or through the chip's flag output with
+//! [`Homing::run_with_flag`] on the LFLAG output, which reports errors as a [`FlagError`].
+//!
+//! ```
+//! use core::cell::RefCell;
+//! use ls7366::Ls7366;
+//! use ls7366::emulator::{EmulatorDevice, Ls7366Emulator};
+//! use ls7366::generator::{ConstantVelocity, QuadratureGenerator, Signalling};
+//! use ls7366::homing::{HomeAction, Homing};
+//!
+//! let chip = RefCell::new(Ls7366Emulator::new());
+//! let mut driver = Ls7366::new(EmulatorDevice::new(&chip)).unwrap();
+//! let mut encoder = QuadratureGenerator::new(Signalling::Quadrature).with_index(1000);
+//!
+//! let report = Homing::new(HomeAction::LoadCount(-50))
+//!     .run(&mut driver, |count| {
+//!         // Move towards the index, giving up after a full revolution.
+//!         for inputs in encoder.run(ConstantVelocity::new(7, 1)) {
+//!             chip.borrow_mut().apply(inputs);
+//!         }
+//!         count < 1000
+//!     })
+//!     .unwrap()
+//!     .expect("index found");
+//! assert_eq!(report.pre_home_position, Some(994));
+//! // The index loaded -50, and the axis moved on by 1 count before the routine noticed.
+//! assert_eq!(report.position, -49);
+//! ```
+//!
+//! [`Homing`]: ./struct.Homing.html
+//! [`Homing::run_with_flag`]: ./struct.Homing.html#method.run_with_flag
+//! [`Str`]: ../ir/enum.Target.html#variant.Str
+//! [`FlagError`]: ../flags/enum.FlagError.html
+
+use embedded_hal::digital::InputPin;
+use embedded_hal::spi::SpiDevice;
+
+use crate::events::Events;
+use crate::flags::FlagError;
+use crate::mdr0::IndexMode;
+use crate::{Error, Ls7366};
+
+/// What the index pulse does to the counter while homing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HomeAction {
+    /// Clear the counter, via [`IndexMode::ClearCntr`].
+    ///
+    /// [`IndexMode::ClearCntr`]: ../mdr0/enum.IndexMode.html#variant.ClearCntr
+    ClearCount,
+    /// Load the counter with an offset, via [`IndexMode::LoadCntr`].
+    ///
+    /// [`IndexMode::LoadCntr`]: ../mdr0/enum.IndexMode.html#variant.LoadCntr
+    LoadCount(i64),
+}
+
+/// Outcome of a successful homing run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HomeReport {
+    /// Last count read before the index pulse, in the old frame of reference.
+    ///
+    /// The pulse itself occurred somewhere between this reading and the next one, so this is
+    /// exact to within the movement between two motion callbacks. `None` if the index had
+    /// already been seen at the first check.
+    pub pre_home_position: Option<i64>,
+    /// Count after homing, in the new frame of reference.
+    pub position: i64,
+}
+
+/// An index homing routine.
+#[derive(Clone, Copy, Debug)]
+pub struct Homing {
+    action: HomeAction,
+}
+
+impl Homing {
+    pub fn new(action: HomeAction) -> Self {
+        Homing { action }
+    }
+
+    /// Homes by polling the [`Str`] index latch.
+    ///
+    /// `motion` is called with the latest count between checks and returns whether to keep
+    /// searching; once it returns `false` the configuration is restored and `None` returned.
+    ///
+    /// [`Str`]: ../ir/enum.Target.html#variant.Str
+    pub fn run<SPI, SpiError, M>(&self, driver: &mut Ls7366<SPI>, motion: M) -> Result<Option<HomeReport>, Error<SpiError>>
+        where SPI: SpiDevice<u8, Error=SpiError>,
+              M: FnMut(i64) -> bool {
+        self.home(driver, None, motion, |driver| {
+            Ok(!driver.take_events(Events::INDEX)?.is_empty())
+        })
+    }
+
+    /// Homes by watching the chip's active low flag output, with the index routed to it for
+    /// the duration of the run.
+    ///
+    /// `flag` must be LFLAG, which stays low until the index latch is consumed. The pin is only
+    /// sampled between motion callbacks, so a DFLAG pulse would usually be missed and the axis
+    /// moved past the index; home with [`run`] when only DFLAG is wired.
+    ///
+    /// `motion` behaves as for [`run`].
+    ///
+    /// [`run`]: #method.run
+    pub fn run_with_flag<SPI, SpiError, PIN, M>(&self, driver: &mut Ls7366<SPI>, flag: &mut PIN, motion: M) -> Result<Option<HomeReport>, FlagError<SpiError, PIN::Error>>
+        where SPI: SpiDevice<u8, Error=SpiError>,
+              PIN: InputPin,
+              M: FnMut(i64) -> bool {
+        let mdr1 = driver.mdr1();
+        let mut flags = Events::INDEX;
+        flags.set(Events::CARRY, mdr1.flag_on_cy);
+        flags.set(Events::BORROW, mdr1.flag_on_bw);
+        flags.set(Events::COMPARE, mdr1.flag_on_cmp);
+
+        self.home(driver, Some(flags), motion, |driver| {
+            if !flag.is_low().map_err(FlagError::Pin)? {
+                return Ok(false);
+            }
+            // The flag may also have been raised by another event.
+            Ok(!driver.take_events(Events::INDEX)?.is_empty())
+        })
+    }
+
+    /// Runs the search, routing `flags` to the flag outputs meanwhile if given, and restores
+    /// the configuration however it ends.
+    fn home<SPI, SpiError, E, M, I>(&self, driver: &mut Ls7366<SPI>, flags: Option<Events>, motion: M, index_seen: I) -> Result<Option<HomeReport>, E>
+        where SPI: SpiDevice<u8, Error=SpiError>,
+              E: From<Error<SpiError>>,
+              M: FnMut(i64) -> bool,
+              I: FnMut(&mut Ls7366<SPI>) -> Result<bool, E> {
+        let (mdr0, mdr1, dtr) = (driver.mdr0(), driver.mdr1(), driver.read_dtr());
+        let searched = self.search(driver, flags, motion, index_seen);
+        let restored = driver.configure(mdr0, mdr1).and_then(|()| driver.write_dtr(dtr));
+        // An error from the search takes precedence over one from restoring.
+        let found = searched?;
+        restored?;
+        match found {
+            Some(pre_home_position) => Ok(Some(HomeReport {
+                pre_home_position,
+                position: driver.get_count()?,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    /// Arms the index and calls `motion` until it is seen, returning the last count before it,
+    /// or `None` if `motion` gave up.
+    fn search<SPI, SpiError, E, M, I>(&self, driver: &mut Ls7366<SPI>, flags: Option<Events>, mut motion: M, mut index_seen: I) -> Result<Option<Option<i64>>, E>
+        where SPI: SpiDevice<u8, Error=SpiError>,
+              E: From<Error<SpiError>>,
+              M: FnMut(i64) -> bool,
+              I: FnMut(&mut Ls7366<SPI>) -> Result<bool, E> {
+        if let Some(flags) = flags {
+            driver.set_flag_events(flags)?;
+        }
+        match self.action {
+            HomeAction::ClearCount => driver.set_index_mode(IndexMode::ClearCntr)?,
+            HomeAction::LoadCount(offset) => driver.preset_count_on_index(offset)?,
+        }
+        // Forget an index seen before the search started.
+        driver.take_events(Events::INDEX)?;
+
+        let mut pre_home_position = None;
+        loop {
+            // The count is read before checking, so it predates the pulse if none was seen.
+            let count = driver.get_count()?;
+            if index_seen(driver)? {
+                return Ok(Some(pre_home_position));
+            }
+            pre_home_position = Some(count);
+            if !motion(count) {
+                return Ok(None);
+            }
+        }
+    }
+}
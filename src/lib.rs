@@ -1,7 +1,17 @@
 //! LS7366 Buffer encoder interface using `embedded_hal`.
 //!
 //! This driver should work with any SPI interface as long as it implements
-//! the blocking `embedded_hal` [`SPI traits`].
+//! the blocking `embedded_hal` 1.0 [`SpiDevice`] trait. Every register operation is issued as a
+//! single transaction, so the chip stays selected for the whole operation.
+//!
+//! Interfaces implementing the embedded-hal 0.2 blocking SPI traits can still be used through
+//! the adapter in the `eh02` module, enabled with the `embedded-hal-02` cargo feature.
+//!
+//! An async driver built on `embedded_hal_async` lives in the `asynch` module, enabled with the
+//! `async` cargo feature.
+//!
+//! The `emulator` module provides a software model of the chip, so code using the driver can be
+//! tested on the host without hardware, and the `generator` module drives it from motion profiles.
 //!
 //! The library is built with `no_std`.
 //!
@@ -13,7 +23,7 @@
 //! // --- snip ---
 //! # use std::error::Error;
 //! #
-//! # use rppal::spi::{Bus, Mode, SlaveSelect, Spi};
+//! # use rppal::spi::{Bus, Mode, SimpleHalSpiDevice, SlaveSelect, Spi};
 //! #
 //! # use std::thread::sleep;
 //! # use std::time::Duration;
@@ -21,7 +31,8 @@
 //! #    // create an instance of an SPI object
 //! #    // In this case, the buffer is on SPI0 and SS1.
 //! #    // The chip acts in Mode0.
-//! #    let some_hal_spi_object = Spi::new(Bus::Spi0, SlaveSelect::Ss1, 14_000_000, Mode::Mode0).unwrap();
+//! #    let spi = Spi::new(Bus::Spi0, SlaveSelect::Ss1, 14_000_000, Mode::Mode0).unwrap();
+//! #    let some_hal_spi_object = SimpleHalSpiDevice::new(spi);
 //! #
 //!     // Construct a driver instance from the SPI interface, using default chip configurations.
 //!     let mut spi_driver = Ls7366::new(some_hal_spi_object).unwrap();
@@ -44,19 +55,26 @@
 //! **Manually configuring these registers is **not** required when using [`Ls7366::new`].**
 //!
 //! 1. Build an instance of [`Mdr0`] and [`Mdr1`] with the desired configuration.
-//! 2. Write these instances into the relevant registers.
+//! 2. Write these instances into the relevant registers with [`Ls7366::configure`].
+//!
+//! Individual fields can later be changed with the per-field setters (e.g. [`set_counter_mode`]),
+//! which modify the driver's cached copy of the configuration.
 //! ```
 //! use ls7366::mdr0::{QuadCountMode, CycleCountMode, FilterClockDivisionFactor,IndexMode, Mdr0};
 //! use ls7366::mdr1::{CounterMode, Mdr1};
-//! use ls7366::{Ls7366, Target, Encodable};
-//! use embedded_hal_mock::spi::Mock;
-//! use embedded_hal_mock::spi::Transaction as SpiTransaction;
+//! use ls7366::Ls7366;
+//! use embedded_hal_mock::eh1::spi::Mock;
+//! use embedded_hal_mock::eh1::spi::Transaction as SpiTransaction;
 //! # let expectations = [
-//! #     SpiTransaction::write(vec![0b10001000, 0b10100110]),
-//! #     SpiTransaction::write(vec![0b10010000, 0b00000101])
+//! #     SpiTransaction::transaction_start(),
+//! #     SpiTransaction::write_vec(vec![0b10001000, 0b10100110]),
+//! #     SpiTransaction::transaction_end(),
+//! #     SpiTransaction::transaction_start(),
+//! #     SpiTransaction::write_vec(vec![0b10010000, 0b00000101]),
+//! #     SpiTransaction::transaction_end(),
 //! # ];
-//! # let spi = Mock::new(&expectations);
-//! # let mut driver = Ls7366::new_uninit(spi);
+//! # let mut spi = Mock::new(&expectations);
+//! # let mut driver = Ls7366::new_uninit(spi.clone());
 //! // --- snip ---
 //!     let mdr0_configuration = Mdr0{
 //!         quad_count_mode: QuadCountMode::Quad2x,
@@ -75,20 +93,26 @@
 //!         # flag_on_cy: false,
 //!     };
 //!
-//!     driver.write_register(Target::Mdr0, &[mdr0_configuration.encode()]).unwrap();
-//!     driver.write_register(Target::Mdr1, &[mdr1_configuration.encode()]).unwrap();
+//!     driver.configure(mdr0_configuration, mdr1_configuration).unwrap();
+//!
+//!     // The driver remembers the configuration it wrote.
+//!     assert_eq!(driver.mdr1().counter_mode, CounterMode::Byte3);
+//! # spi.done();
 //!
 //! ```
 //!
-//! [`SPI traits`]: https://docs.rs/embedded-hal/0.2.3/embedded_hal/blocking/spi/index.html
+//! [`SpiDevice`]: https://docs.rs/embedded-hal/1.0.0/embedded_hal/spi/trait.SpiDevice.html
 //! [`Mdr0`]: ./mdr0/struct.Mdr0.html
 //! [`Mdr1`]: ./mdr1/struct.Mdr1.html
 //! [`Ls7366::new`]: ./struct.Ls7366.html#method.new
+//! [`Ls7366::configure`]: ./struct.Ls7366.html#method.configure
+//! [`set_counter_mode`]: ./struct.Ls7366.html#method.set_counter_mode
 #![cfg_attr(not(test), no_std)]
 
-use embedded_hal::blocking::spi::{Transfer, Write};
+use embedded_hal::spi::{Operation, SpiDevice};
 
 pub use crate::ir::{Action, Target};
+use crate::events::Events;
 use crate::ir::InstructionRegister;
 use crate::str_register::Str;
 use crate::traits::Decodable;
@@ -98,10 +122,28 @@ pub mod mdr0;
 pub mod ir;
 pub mod mdr1;
 pub mod str_register;
+pub mod multi_axis;
+pub mod bus;
+pub mod clock_direction;
+pub mod compare;
+pub mod emulator;
+pub mod events;
+pub mod flags;
+pub mod generator;
+pub mod geometry;
+pub mod homing;
+pub mod tracker;
+pub mod velocity;
+#[cfg(feature = "embedded-hal-02")]
+pub mod eh02;
+#[cfg(feature = "async")]
+pub mod asynch;
 mod traits;
 mod errors;
 mod utilities;
+mod registers;
 mod test_instruction_register;
+mod test_mdr0;
 
 #[derive(Clone, Debug)]
 pub enum Error<SpiError> {
@@ -111,6 +153,33 @@ pub enum Error<SpiError> {
     EncodeError(errors::EncoderError),
     // Request to write payload larger than target register.
     PayloadTooBig,
+    // Value does not fit into the configured counter width.
+    ValueOutOfRange,
+    // Configuration read back from the chip differs from the driver's configuration.
+    ConfigurationMismatch(ConfigurationMismatch),
+    // The configured cycle count mode does not suit the requested operation.
+    UnsupportedCycleCountMode,
+}
+
+/// Configuration registers as intended by the driver and as read back from the chip.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigurationMismatch {
+    /// [`Mdr0`] configuration the driver last wrote.
+    ///
+    /// [`Mdr0`]: ./mdr0/struct.Mdr0.html
+    pub expected_mdr0: mdr0::Mdr0,
+    /// [`Mdr0`] configuration read back from the chip.
+    ///
+    /// [`Mdr0`]: ./mdr0/struct.Mdr0.html
+    pub actual_mdr0: mdr0::Mdr0,
+    /// [`Mdr1`] configuration the driver last wrote.
+    ///
+    /// [`Mdr1`]: ./mdr1/struct.Mdr1.html
+    pub expected_mdr1: mdr1::Mdr1,
+    /// [`Mdr1`] configuration read back from the chip.
+    ///
+    /// [`Mdr1`]: ./mdr1/struct.Mdr1.html
+    pub actual_mdr1: mdr1::Mdr1,
 }
 
 
@@ -126,14 +195,68 @@ impl<E> From<E> for Error<E> {
     }
 }
 
+/// A count and the status register, captured together by [`Ls7366::snapshot`].
+///
+/// [`Ls7366::snapshot`]: ./struct.Ls7366.html#method.snapshot
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Snapshot {
+    /// Counter value latched into [`Otr`].
+    ///
+    /// [`Otr`]:  ir/enum.Target.html#variant.Otr
+    pub count: i64,
+    /// Status register read immediately after latching.
+    pub status: Str,
+}
+
+/// How the driver watches the [`Str`] power-loss latch, see [`Ls7366::set_supervision`].
+///
+/// The default only checks when [`Ls7366::check_power_loss`] is called and does not restore the
+/// count.
+///
+/// [`Str`]:  ir/enum.Target.html#variant.Str
+/// [`Ls7366::set_supervision`]: ./struct.Ls7366.html#method.set_supervision
+/// [`Ls7366::check_power_loss`]: ./struct.Ls7366.html#method.check_power_loss
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Supervision {
+    /// Also check after every [`get_count`], [`snapshot`] and [`poll_events`].
+    ///
+    /// [`get_count`]: ./struct.Ls7366.html#method.get_count
+    /// [`snapshot`]: ./struct.Ls7366.html#method.snapshot
+    /// [`poll_events`]: ./struct.Ls7366.html#method.poll_events
+    pub check_on_read: bool,
+    /// Load the last count read or set through the driver back into the counter on recovery,
+    /// instead of leaving it at zero.
+    pub restore_count: bool,
+}
+
 /// An LS8366 Quadrature encoder buffer
 pub struct Ls7366<SPI> {
     /// SPI interface where the buffer is attached.
     interface: SPI,
+    /// Last values written into the configuration registers and [`Dtr`].
+    ///
+    /// [`Dtr`]:  ir/enum.Target.html#variant.Dtr
+    registers: registers::Registers,
+    supervision: Supervision,
+    /// Last count read or set, restored after a power loss if enabled.
+    last_count: i64,
+    /// Events noticed while supervising, not yet returned by [`poll_events`].
+    ///
+    /// [`poll_events`]: #method.poll_events
+    pending_events: Events,
+    /// A power loss whose latch was cleared along with other events before the chip was
+    /// recovered, still to be handled by [`check_power_loss`].
+    ///
+    /// [`check_power_loss`]: #method.check_power_loss
+    unrecovered_power_loss: bool,
+    /// Whether [`single_cycle_done`] reloads the counter once the cycle has ended.
+    ///
+    /// [`single_cycle_done`]: #method.single_cycle_done
+    single_cycle_reload: bool,
 }
 
 impl<SPI, SpiError> Ls7366<SPI>
-    where SPI: Transfer<u8, Error=SpiError> + Write<u8, Error=SpiError> {
+    where SPI: SpiDevice<u8, Error=SpiError> {
     /// Creates a new driver and initializes the Chip to some sensible default values.
     /// This will zero the chip's counter, configure it to 4 byte count mode (full range)
     /// and to treat every 4th quadrature pulse as a increment.
@@ -143,74 +266,337 @@ impl<SPI, SpiError> Ls7366<SPI>
     ///
     /// [`uninit`]: #method.new_uninit
     pub fn new(iface: SPI) -> Result<Self, Error<SpiError>> {
-        let mut driver = Ls7366 {
-            interface: iface
-        };
-        // Creating configurations for the two MDR configuration registers
-        let mdr0_payload = mdr0::Mdr0 {
-            quad_count_mode: mdr0::QuadCountMode::Quad4x,
-            cycle_count_mode: mdr0::CycleCountMode::FreeRunning,
-            index_mode: mdr0::IndexMode::DisableIndex,
-            is_index_inverted: false,
-            filter_clock: mdr0::FilterClockDivisionFactor::One,
-        };
-        let mdr1_payload = mdr1::Mdr1 {
-            counter_mode: mdr1::CounterMode::Byte4,
-            disable_counting: false,
-            flag_on_idx: false,
-            flag_on_cmp: false,
-            flag_on_bw: false,
-            flag_on_cy: false,
-        };
-
-        // Write primary configuration to chip.
-        driver.write_register(ir::Target::Mdr0, &[mdr0_payload.encode()])?;
-        // Write secondary configuration to chip.
-        driver.write_register(ir::Target::Mdr1, &[mdr1_payload.encode()])?;
-        // Zero Dtr to prepare a write into Cntr.
-        driver.write_register(ir::Target::Dtr, &[0x00, 0x00, 0x00, 0x00])?;
-        // Load Dtr into Cntr.
-        driver.act(
-            ir::InstructionRegister {
-                target: ir::Target::Cntr,
-                action: ir::Action::Load,
-            },
-            &mut [0x00],
-        )?;
+        let mut driver = Ls7366::new_uninit(iface);
+        // Write primary and secondary configuration to chip.
+        driver.configure(registers::INITIAL_MDR0, registers::INITIAL_MDR1)?;
+        // Zero the counter through Dtr.
+        driver.set_count(0)?;
         // clear status register.
         driver.clear_status()?;
         Ok(driver)
     }
 
     /// Creates a new driver but does NOT do any initialization actions against the chip.
+    ///
+    /// The driver assumes the chip's power-on configuration until [`configure`] is called or
+    /// the configuration registers are written.
+    ///
+    /// [`configure`]: #method.configure
     pub fn new_uninit(iface: SPI) -> Self {
         Ls7366 {
-            interface: iface
+            interface: iface,
+            registers: registers::Registers::default(),
+            supervision: Supervision::default(),
+            last_count: 0,
+            pending_events: Events::empty(),
+            unrecovered_power_loss: false,
+            single_cycle_reload: false,
+        }
+    }
+
+    /// Writes both configuration registers to the chip and caches them in the driver.
+    pub fn configure(&mut self, mdr0: mdr0::Mdr0, mdr1: mdr1::Mdr1) -> Result<(), Error<SpiError>> {
+        self.write_mdr0(mdr0)?;
+        self.write_mdr1(mdr1)
+    }
+
+    /// Returns the cached primary configuration.
+    pub fn mdr0(&self) -> mdr0::Mdr0 {
+        self.registers.mdr0
+    }
+
+    /// Returns the cached secondary configuration.
+    pub fn mdr1(&self) -> mdr1::Mdr1 {
+        self.registers.mdr1
+    }
+
+    /// Changes the quadrature count mode, preserving the rest of the cached [`Mdr0`].
+    ///
+    /// [`Mdr0`]: mdr0/struct.Mdr0.html
+    pub fn set_quad_mode(&mut self, quad_count_mode: mdr0::QuadCountMode) -> Result<(), Error<SpiError>> {
+        let mut mdr0 = self.registers.mdr0;
+        mdr0.quad_count_mode = quad_count_mode;
+        self.write_mdr0(mdr0)
+    }
+
+    /// Changes the cycle count mode, preserving the rest of the cached [`Mdr0`].
+    ///
+    /// [`Mdr0`]: mdr0/struct.Mdr0.html
+    pub fn set_cycle_count_mode(&mut self, cycle_count_mode: mdr0::CycleCountMode) -> Result<(), Error<SpiError>> {
+        let mut mdr0 = self.registers.mdr0;
+        mdr0.cycle_count_mode = cycle_count_mode;
+        self.write_mdr0(mdr0)
+    }
+
+    /// Changes the behavior of the index pin, preserving the rest of the cached [`Mdr0`].
+    ///
+    /// [`Mdr0`]: mdr0/struct.Mdr0.html
+    pub fn set_index_mode(&mut self, index_mode: mdr0::IndexMode) -> Result<(), Error<SpiError>> {
+        let mut mdr0 = self.registers.mdr0;
+        mdr0.index_mode = index_mode;
+        self.write_mdr0(mdr0)
+    }
+
+    /// Changes the index filter clock, preserving the rest of the cached [`Mdr0`].
+    ///
+    /// [`Mdr0`]: mdr0/struct.Mdr0.html
+    pub fn set_filter_clock(&mut self, filter_clock: mdr0::FilterClockDivisionFactor) -> Result<(), Error<SpiError>> {
+        let mut mdr0 = self.registers.mdr0;
+        mdr0.filter_clock = filter_clock;
+        self.write_mdr0(mdr0)
+    }
+
+    /// Changes the counter width, preserving the rest of the cached [`Mdr1`].
+    ///
+    /// [`Mdr1`]: mdr1/struct.Mdr1.html
+    pub fn set_counter_mode(&mut self, counter_mode: mdr1::CounterMode) -> Result<(), Error<SpiError>> {
+        let mut mdr1 = self.registers.mdr1;
+        mdr1.counter_mode = counter_mode;
+        self.write_mdr1(mdr1)
+    }
+
+    /// Enables (false) or disables (true) counting, preserving the rest of the cached [`Mdr1`].
+    ///
+    /// [`Mdr1`]: mdr1/struct.Mdr1.html
+    pub fn set_disable_counting(&mut self, disable_counting: bool) -> Result<(), Error<SpiError>> {
+        let mut mdr1 = self.registers.mdr1;
+        mdr1.disable_counting = disable_counting;
+        self.write_mdr1(mdr1)
+    }
+
+    /// Chooses which latched events assert the chip's flag outputs, preserving the rest of the
+    /// cached [`Mdr1`].
+    ///
+    /// Only [`Events::CARRY`], [`Events::BORROW`], [`Events::COMPARE`] and [`Events::INDEX`] can
+    /// be routed to the flag outputs; other events are ignored.
+    ///
+    /// [`Mdr1`]: mdr1/struct.Mdr1.html
+    /// [`Events::CARRY`]: events/struct.Events.html#associatedconstant.CARRY
+    /// [`Events::BORROW`]: events/struct.Events.html#associatedconstant.BORROW
+    /// [`Events::COMPARE`]: events/struct.Events.html#associatedconstant.COMPARE
+    /// [`Events::INDEX`]: events/struct.Events.html#associatedconstant.INDEX
+    pub fn set_flag_events(&mut self, events: Events) -> Result<(), Error<SpiError>> {
+        let mut mdr1 = self.registers.mdr1;
+        mdr1.flag_on_cy = events.contains(Events::CARRY);
+        mdr1.flag_on_bw = events.contains(Events::BORROW);
+        mdr1.flag_on_cmp = events.contains(Events::COMPARE);
+        mdr1.flag_on_idx = events.contains(Events::INDEX);
+        self.write_mdr1(mdr1)
+    }
+
+    /// Reads the primary configuration back from the chip.
+    ///
+    /// This does not touch the driver's cached configuration.
+    pub fn read_mdr0(&mut self) -> Result<mdr0::Mdr0, Error<SpiError>> {
+        let raw_result: &mut [u8] = &mut [0x00];
+        let raw_result = self.read_register(raw_result, ir::Target::Mdr0)?;
+        mdr0::Mdr0::decode(raw_result[0]).map_err(Error::EncodeError)
+    }
+
+    /// Reads the secondary configuration back from the chip.
+    ///
+    /// This does not touch the driver's cached configuration.
+    pub fn read_mdr1(&mut self) -> Result<mdr1::Mdr1, Error<SpiError>> {
+        let raw_result: &mut [u8] = &mut [0x00];
+        let raw_result = self.read_register(raw_result, ir::Target::Mdr1)?;
+        mdr1::Mdr1::decode(raw_result[0]).map_err(Error::EncodeError)
+    }
+
+    /// Reads both configuration registers back from the chip and compares them against the
+    /// driver's cached configuration.
+    ///
+    /// Any difference is reported as an [`Error::ConfigurationMismatch`], which is a good
+    /// indication the chip is not responding (e.g. a floating select line) or was reset.
+    ///
+    /// [`Error::ConfigurationMismatch`]: ./enum.Error.html#variant.ConfigurationMismatch
+    pub fn verify_configuration(&mut self) -> Result<(), Error<SpiError>> {
+        let actual_mdr0 = self.read_mdr0()?;
+        let actual_mdr1 = self.read_mdr1()?;
+        if actual_mdr0 == self.registers.mdr0 && actual_mdr1 == self.registers.mdr1 {
+            Ok(())
+        } else {
+            Err(Error::ConfigurationMismatch(ConfigurationMismatch {
+                expected_mdr0: self.registers.mdr0,
+                actual_mdr0,
+                expected_mdr1: self.registers.mdr1,
+                actual_mdr1,
+            }))
         }
     }
+
+    /// Writes `value` into [`Dtr`], using exactly as many bytes as the configured
+    /// [`CounterMode`].
+    ///
+    /// Values that do not fit into the configured width are rejected with
+    /// [`Error::ValueOutOfRange`] without touching the chip.
+    ///
+    /// [`Dtr`]:  ir/enum.Target.html#variant.Dtr
+    /// [`CounterMode`]: mdr1/enum.CounterMode.html
+    /// [`Error::ValueOutOfRange`]: ./enum.Error.html#variant.ValueOutOfRange
+    pub fn write_dtr(&mut self, value: u32) -> Result<(), Error<SpiError>> {
+        let bytes = self.registers.encode_dtr(value).ok_or(Error::ValueOutOfRange)?;
+        self.write_register(ir::Target::Dtr, &bytes[4 - self.registers.width()..])
+    }
+
+    /// Returns the value last written into [`Dtr`].
+    ///
+    /// The chip offers no instruction to read [`Dtr`] back; the only way to observe it is to load
+    /// it into [`Cntr`], which would disturb counting. The driver therefore answers from the value
+    /// it last wrote, which is zero after power-on.
+    ///
+    /// [`Dtr`]:  ir/enum.Target.html#variant.Dtr
+    /// [`Cntr`]: ir/enum.Target.html#variant.Cntr
+    pub fn read_dtr(&self) -> u32 {
+        self.registers.dtr
+    }
+
+    /// Sets the counter to `value` by writing it into [`Dtr`] and loading [`Dtr`] into [`Cntr`].
+    ///
+    /// `value` must fit into the configured [`CounterMode`] as a signed number, otherwise
+    /// [`Error::ValueOutOfRange`] is returned. In the range-limit and modulo-N cycle count modes
+    /// it must instead lie between zero and the limit held in [`Dtr`], which is written back
+    /// afterwards.
+    ///
+    /// [`Dtr`]:  ir/enum.Target.html#variant.Dtr
+    /// [`Cntr`]: ir/enum.Target.html#variant.Cntr
+    /// [`CounterMode`]: mdr1/enum.CounterMode.html
+    /// [`Error::ValueOutOfRange`]: ./enum.Error.html#variant.ValueOutOfRange
+    pub fn set_count(&mut self, value: i64) -> Result<(), Error<SpiError>> {
+        let limit = self.registers.dtr;
+        self.write_signed_dtr(value)?;
+        self.act(
+            ir::InstructionRegister {
+                target: Target::Cntr,
+                action: Action::Load,
+            }, &mut [0x00],
+        )?;
+        if self.registers.mdr0.cycle_count_mode.is_bounded() {
+            self.write_dtr(limit)?;
+        }
+        self.last_count = value;
+        Ok(())
+    }
+
+    /// Clears the [`Cntr`] counter register to zero.
+    ///
+    /// Unlike [`set_count`], this leaves [`Dtr`] untouched.
+    ///
+    /// [`Cntr`]: ir/enum.Target.html#variant.Cntr
+    /// [`Dtr`]:  ir/enum.Target.html#variant.Dtr
+    /// [`set_count`]: #method.set_count
+    pub fn clear_count(&mut self) -> Result<(), Error<SpiError>> {
+        self.act(
+            ir::InstructionRegister {
+                target: Target::Cntr,
+                action: Action::Clear,
+            }, &mut [0x00],
+        )?;
+        self.last_count = 0;
+        Ok(())
+    }
+
+    /// Arranges for the counter to be set to `value` on the next index pulse.
+    ///
+    /// `value` is written into [`Dtr`] and the index pin is configured as
+    /// [`IndexMode::LoadCntr`].
+    ///
+    /// [`Dtr`]:  ir/enum.Target.html#variant.Dtr
+    /// [`IndexMode::LoadCntr`]: mdr0/enum.IndexMode.html#variant.LoadCntr
+    pub fn preset_count_on_index(&mut self, value: i64) -> Result<(), Error<SpiError>> {
+        self.write_signed_dtr(value)?;
+        self.set_index_mode(mdr0::IndexMode::LoadCntr)
+    }
+
+    /// Counts up to `max` and down to zero, freezing at either limit until the direction
+    /// reverses.
+    ///
+    /// Sets [`Dtr`] to `max` and the cycle count mode to [`CycleCountMode::RangeLimit`]. From then
+    /// on counts are unsigned: [`get_count`] returns `0..=max` and [`set_count`] accepts the same,
+    /// returning [`Error::ValueOutOfRange`] for anything else. The count itself is left alone and
+    /// should be brought into range with [`set_count`] if needed. `max` must fit into the
+    /// configured [`CounterMode`].
+    ///
+    /// [`Dtr`]:  ir/enum.Target.html#variant.Dtr
+    /// [`CycleCountMode::RangeLimit`]: mdr0/enum.CycleCountMode.html#variant.RangeLimit
+    /// [`get_count`]: #method.get_count
+    /// [`set_count`]: #method.set_count
+    /// [`CounterMode`]: mdr1/enum.CounterMode.html
+    /// [`Error::ValueOutOfRange`]: ./enum.Error.html#variant.ValueOutOfRange
+    pub fn configure_range_limit(&mut self, max: u32) -> Result<(), Error<SpiError>> {
+        self.write_dtr(max)?;
+        self.set_cycle_count_mode(mdr0::CycleCountMode::RangeLimit)
+    }
+
+    /// Counts modulo `n`, wrapping from `n - 1` to zero and back.
+    ///
+    /// Sets [`Dtr`] to `n - 1` and the cycle count mode to [`CycleCountMode::ModuloN`], so e.g.
+    /// a rotary encoder with `n` counts per revolution reports its angle as `0..n`. From then on
+    /// counts are unsigned: [`get_count`] returns `0..n` and [`set_count`] accepts the same,
+    /// returning [`Error::ValueOutOfRange`] for anything else. The count itself is left alone and
+    /// should be brought into range with [`set_count`] if needed.
+    ///
+    /// `n` must be at least 1 and `n - 1` must fit into the configured [`CounterMode`], otherwise
+    /// [`Error::ValueOutOfRange`] is returned.
+    ///
+    /// [`Dtr`]:  ir/enum.Target.html#variant.Dtr
+    /// [`CycleCountMode::ModuloN`]: mdr0/enum.CycleCountMode.html#variant.ModuloN
+    /// [`get_count`]: #method.get_count
+    /// [`set_count`]: #method.set_count
+    /// [`CounterMode`]: mdr1/enum.CounterMode.html
+    /// [`Error::ValueOutOfRange`]: ./enum.Error.html#variant.ValueOutOfRange
+    pub fn configure_modulo(&mut self, n: u32) -> Result<(), Error<SpiError>> {
+        let max = n.checked_sub(1).ok_or(Error::ValueOutOfRange)?;
+        self.write_dtr(max)?;
+        self.set_cycle_count_mode(mdr0::CycleCountMode::ModuloN)
+    }
+
+    fn write_signed_dtr(&mut self, value: i64) -> Result<(), Error<SpiError>> {
+        let raw = self.registers.encode_count(value).ok_or(Error::ValueOutOfRange)?;
+        self.write_dtr(raw)
+    }
+
+    fn write_mdr0(&mut self, mdr0: mdr0::Mdr0) -> Result<(), Error<SpiError>> {
+        self.write_register(ir::Target::Mdr0, &[mdr0.encode()])?;
+        self.registers.mdr0 = mdr0;
+        Ok(())
+    }
+
+    fn write_mdr1(&mut self, mdr1: mdr1::Mdr1) -> Result<(), Error<SpiError>> {
+        self.write_register(ir::Target::Mdr1, &[mdr1.encode()])?;
+        self.registers.mdr1 = mdr1;
+        Ok(())
+    }
+
     /// Writes bytes into the specified register. attempting to write more than 4 bytes is an error.
+    ///
+    /// Writes into [`Mdr0`], [`Mdr1`] and [`Dtr`] also update the driver's cached configuration.
+    ///
+    /// [`Mdr0`]: ir/enum.Target.html#variant.Mdr0
+    /// [`Mdr1`]: ir/enum.Target.html#variant.Mdr1
+    /// [`Dtr`]: ir/enum.Target.html#variant.Dtr
     pub fn write_register(&mut self, target: ir::Target, data: &[u8]) -> Result<(), Error<SpiError>> {
+        if data.len() > 4 {
+            return Err(Error::PayloadTooBig);
+        }
+        let registers = self.registers.after_write(&target, data).map_err(Error::EncodeError)?;
         let ir_cmd = ir::InstructionRegister {
             target,
             action: ir::Action::Write,
         };
-        if data.len() > 4 {
-            return Err(Error::PayloadTooBig);
-        }
 
         let encoded = ir_cmd.encode();
         let payload: &mut [u8] = &mut [encoded, encoded, encoded, encoded, encoded];
+        payload[1..=data.len()].copy_from_slice(data);
 
-        let mut i = 1;
-        for datum in data {
-            payload[i] = *datum;
-            i+=1;
-        }
         // only write as many bits as we had data, +1 for the IR.
         self.interface.write(&payload[0.. data.len()+1])?;
+        self.registers = registers;
         Ok(())
     }
-    /// Executes a read operation against specified register, returning up to 4 bytes from the chip.
+    /// Executes a read operation against specified register, filling `rx_buffer` with up to
+    /// 4 bytes from the chip.
+    ///
+    /// Exactly `rx_buffer.len()` bytes are clocked out of the chip after the instruction.
     ///
     /// ## Note:
     ///
@@ -229,10 +615,15 @@ impl<SPI, SpiError> Ls7366<SPI>
             target,
             action: Action::Read,
         };
-        let tx_buffer = &mut [ir.encode(), 0x00, 0x00, 0x00, 0x00];
-
-        let result = self.interface.transfer(tx_buffer)?;
-        rx_buffer.copy_from_slice(&result[1..]);
+        if rx_buffer.len() > 4 {
+            return Err(Error::PayloadTooBig);
+        }
+        // The instruction and the register contents share one transaction, so the chip stays
+        // selected for the whole operation.
+        self.interface.transaction(&mut [
+            Operation::Write(&[ir.encode()]),
+            Operation::Read(rx_buffer),
+        ])?;
         Ok(rx_buffer)
     }
     pub fn get_status(&mut self) -> Result<Str, Error<SpiError>> {
@@ -256,16 +647,300 @@ impl<SPI, SpiError> Ls7366<SPI>
         )?;
         Ok(())
     }
-    /// Reads the chip's current count, sets the sign bit appropriate to the status register
+    /// Reads the chip's current count.
+    ///
+    /// Exactly as many bytes as the configured [`CounterMode`] are read, and the result is
+    /// sign-extended from that width as the chip counts in two's complement. In the range-limit
+    /// and modulo-N cycle count modes the count is bounded by [`Dtr`] and returned unsigned, e.g.
+    /// `0..n` after [`configure_modulo`].
+    ///
+    /// [`CounterMode`]: mdr1/enum.CounterMode.html
+    /// [`Dtr`]:  ir/enum.Target.html#variant.Dtr
+    /// [`configure_modulo`]: #method.configure_modulo
+    ///
+    /// With [`Supervision::check_on_read`] set, the power-loss latch is checked afterwards, and
+    /// the count is read again once the chip has been recovered.
+    ///
+    /// [`Supervision::check_on_read`]: ./struct.Supervision.html#structfield.check_on_read
     pub fn get_count(&mut self) -> Result<i64, Error<SpiError>> {
-        let raw_result: &mut [u8] = &mut [0x00, 0x00, 0x00, 0x00];
-        let raw_result = self.read_register(raw_result,ir::Target::Cntr)?;
+        let mut count = self.read_count_register(ir::Target::Cntr)?;
+        if self.supervision.check_on_read && self.check_power_loss()? {
+            count = self.read_count_register(ir::Target::Cntr)?;
+        }
+        self.last_count = count;
+        Ok(count)
+    }
+
+    /// Latches the instantaneous value of [`Cntr`] into [`Otr`] without disturbing counting.
+    ///
+    /// [`Cntr`]: ir/enum.Target.html#variant.Cntr
+    /// [`Otr`]:  ir/enum.Target.html#variant.Otr
+    pub fn latch_count(&mut self) -> Result<(), Error<SpiError>> {
+        self.act(
+            ir::InstructionRegister {
+                target: Target::Otr,
+                action: Action::Load,
+            }, &mut [0x00],
+        )?;
+        Ok(())
+    }
+
+    /// Reads the count previously latched into [`Otr`], decoded like [`get_count`].
+    ///
+    /// [`Otr`]:  ir/enum.Target.html#variant.Otr
+    /// [`get_count`]: #method.get_count
+    pub fn read_latched_count(&mut self) -> Result<i64, Error<SpiError>> {
+        self.read_count_register(ir::Target::Otr)
+    }
+
+    /// Latches the count and captures the status register alongside it.
+    ///
+    /// The status is read right after the latch, so sign and direction bits describe the
+    /// latched count rather than a later one.
+    ///
+    /// With [`Supervision::check_on_read`] set, a power loss shown by the status is recovered
+    /// from and the snapshot is taken again.
+    ///
+    /// [`Supervision::check_on_read`]: ./struct.Supervision.html#structfield.check_on_read
+    pub fn snapshot(&mut self) -> Result<Snapshot, Error<SpiError>> {
+        let mut snapshot = self.take_snapshot()?;
+        if self.supervision.check_on_read && self.handle_status(&snapshot.status)? {
+            snapshot = self.take_snapshot()?;
+        }
+        self.last_count = snapshot.count;
+        Ok(snapshot)
+    }
+
+    fn take_snapshot(&mut self) -> Result<Snapshot, Error<SpiError>> {
+        self.latch_count()?;
         let status = self.get_status()?;
-        let count = utilities::vec_to_i64(&raw_result);
-        match status.sign_bit {
-            str_register::SignBit::Negative => Ok(count * -1),
-            str_register::SignBit::Positive => Ok(count),
+        let count = self.read_latched_count()?;
+        Ok(Snapshot { count, status })
+    }
+
+    /// Reads and clears the [`Str`] latches, returning the events recorded since the last poll.
+    ///
+    /// [`Str`] is only cleared when something is latched, so polling an idle chip cannot lose an
+    /// event. Otherwise the read and the clear are issued back to back, and an event latched in
+    /// the few SPI clock cycles between the two instructions is lost.
+    ///
+    /// [`Str`]:  ir/enum.Target.html#variant.Str
+    ///
+    /// Events noticed while supervising for power loss are included, as is
+    /// [`Events::POWER_LOSS_RECOVERED`] once the driver has recovered the chip.
+    ///
+    /// [`Events::POWER_LOSS_RECOVERED`]: events/struct.Events.html#associatedconstant.POWER_LOSS_RECOVERED
+    pub fn poll_events(&mut self) -> Result<Events, Error<SpiError>> {
+        let status = self.get_status()?;
+        let taken = self.take_from_status(&status, Events::all())?;
+        let events = self.pending_events | taken;
+        self.pending_events = Events::empty();
+        Ok(events)
+    }
+
+    /// Arms the compare trigger: discards any stale compare latch, then writes `position` into
+    /// [`Dtr`] and routes compare matches to the flag outputs.
+    ///
+    /// The compare latch is set when the counter next becomes equal to `position`; a counter
+    /// already there has to move away and back first. As the stale latch is discarded first, a
+    /// match right after arming is kept for [`check_compare`]. Other latched events are kept for
+    /// [`poll_events`]. Note that [`Dtr`] also sets the range in the range-limit and modulo-N
+    /// cycle modes.
+    ///
+    /// [`Dtr`]:  ir/enum.Target.html#variant.Dtr
+    /// [`check_compare`]: #method.check_compare
+    /// [`poll_events`]: #method.poll_events
+    pub fn arm_compare(&mut self, position: i64) -> Result<(), Error<SpiError>> {
+        self.take_events(Events::COMPARE)?;
+        self.write_signed_dtr(position)?;
+        let mut mdr1 = self.registers.mdr1;
+        mdr1.flag_on_cmp = true;
+        self.write_mdr1(mdr1)
+    }
+
+    /// Checks whether the counter has reached the armed compare position, consuming the
+    /// compare latch if so.
+    ///
+    /// Other latched events are kept for [`poll_events`].
+    ///
+    /// [`poll_events`]: #method.poll_events
+    pub fn check_compare(&mut self) -> Result<bool, Error<SpiError>> {
+        Ok(!self.take_events(Events::COMPARE)?.is_empty())
+    }
+
+    /// Blocks until the counter reaches the armed compare position.
+    pub fn wait_for_compare(&mut self) -> Result<(), Error<SpiError>> {
+        while !self.check_compare()? {}
+        Ok(())
+    }
+
+    /// Starts a single counting cycle from `preset`.
+    ///
+    /// Switches to the single-cycle count mode if needed, then loads `preset` through [`Dtr`]
+    /// into [`Cntr`], which also re-enables counting, and discards stale carry and borrow
+    /// latches. The chip stops counting on the next carry or borrow: counting up from `-n`
+    /// it stops on the `n`th count, at zero, and counting down from `n - 1` on the `n`th count,
+    /// at -1.
+    ///
+    /// [`Dtr`]:  ir/enum.Target.html#variant.Dtr
+    /// [`Cntr`]: ir/enum.Target.html#variant.Cntr
+    pub fn start_single_cycle(&mut self, preset: i64) -> Result<(), Error<SpiError>> {
+        if self.registers.mdr0.cycle_count_mode != mdr0::CycleCountMode::SingleCycle {
+            self.set_cycle_count_mode(mdr0::CycleCountMode::SingleCycle)?;
         }
+        self.set_count(preset)?;
+        self.take_events(Events::CARRY | Events::BORROW)?;
+        Ok(())
+    }
+
+    /// Checks whether the cycle started by [`start_single_cycle`] has ended, i.e. the chip has
+    /// stopped counting after a carry or borrow. The carry and borrow latches are consumed,
+    /// other latched events are kept for [`poll_events`].
+    ///
+    /// With [`set_single_cycle_reload`] enabled, an ended cycle is restarted right away by
+    /// loading [`Dtr`] into [`Cntr`] again, so `true` is returned once per cycle. This relies on
+    /// [`Dtr`] still holding the preset. Otherwise `true` is returned until the next start.
+    ///
+    /// While counting is disabled through [`Mdr1::disable_counting`] the chip is not counting
+    /// for that reason, so `false` is returned without reading it and the latches are left for
+    /// when counting resumes.
+    ///
+    /// [`start_single_cycle`]: #method.start_single_cycle
+    /// [`Mdr1::disable_counting`]: mdr1/struct.Mdr1.html#structfield.disable_counting
+    /// [`set_single_cycle_reload`]: #method.set_single_cycle_reload
+    /// [`poll_events`]: #method.poll_events
+    /// [`Dtr`]:  ir/enum.Target.html#variant.Dtr
+    /// [`Cntr`]: ir/enum.Target.html#variant.Cntr
+    pub fn single_cycle_done(&mut self) -> Result<bool, Error<SpiError>> {
+        if self.registers.mdr1.disable_counting {
+            return Ok(false);
+        }
+        let status = self.get_status()?;
+        let taken = self.take_from_status(&status, Events::CARRY | Events::BORROW)?;
+        let done = !status.count_enabled || !taken.is_empty();
+        if done && self.single_cycle_reload {
+            self.act(
+                ir::InstructionRegister {
+                    target: Target::Cntr,
+                    action: Action::Load,
+                }, &mut [0x00],
+            )?;
+        }
+        Ok(done)
+    }
+
+    /// Makes [`single_cycle_done`] restart an ended cycle from the same preset.
+    ///
+    /// [`single_cycle_done`]: #method.single_cycle_done
+    pub fn set_single_cycle_reload(&mut self, reload: bool) {
+        self.single_cycle_reload = reload;
+    }
+
+    /// Returns whether [`single_cycle_done`] restarts ended cycles.
+    ///
+    /// [`single_cycle_done`]: #method.single_cycle_done
+    pub fn single_cycle_reload(&self) -> bool {
+        self.single_cycle_reload
+    }
+
+    /// Changes how the driver watches for power loss.
+    pub fn set_supervision(&mut self, supervision: Supervision) {
+        self.supervision = supervision;
+    }
+
+    /// Returns the current power-loss supervision settings.
+    pub fn supervision(&self) -> Supervision {
+        self.supervision
+    }
+
+    /// Checks the [`Str`] power-loss latch, recovering the chip if it is set.
+    ///
+    /// A power loss already seen in [`Str`] by the driver without [`Supervision::check_on_read`],
+    /// e.g. by [`poll_events`], [`check_compare`] or a [`PositionTracker`] update, is recovered
+    /// from here as well.
+    ///
+    /// Recovery reapplies the cached [`Mdr0`], [`Mdr1`] and [`Dtr`], restores the last count if
+    /// [`Supervision::restore_count`] is set, clears [`Str`] and queues
+    /// [`Events::POWER_LOSS`] and [`Events::POWER_LOSS_RECOVERED`] for [`poll_events`].
+    /// Other latched events seen along the way are queued as well, so none are lost.
+    ///
+    /// Returns whether the chip had lost power.
+    ///
+    /// [`Str`]:  ir/enum.Target.html#variant.Str
+    /// [`Mdr0`]: ir/enum.Target.html#variant.Mdr0
+    /// [`Mdr1`]: ir/enum.Target.html#variant.Mdr1
+    /// [`Dtr`]:  ir/enum.Target.html#variant.Dtr
+    /// [`check_compare`]: #method.check_compare
+    /// [`PositionTracker`]: tracker/struct.PositionTracker.html
+    /// [`Supervision::check_on_read`]: ./struct.Supervision.html#structfield.check_on_read
+    /// [`Supervision::restore_count`]: ./struct.Supervision.html#structfield.restore_count
+    /// [`Events::POWER_LOSS`]: events/struct.Events.html#associatedconstant.POWER_LOSS
+    /// [`Events::POWER_LOSS_RECOVERED`]: events/struct.Events.html#associatedconstant.POWER_LOSS_RECOVERED
+    /// [`poll_events`]: #method.poll_events
+    pub fn check_power_loss(&mut self) -> Result<bool, Error<SpiError>> {
+        let status = self.get_status()?;
+        self.handle_status(&status)
+    }
+
+    /// Reads [`Str`] and returns which of the `consumed` events are latched, clearing it if
+    /// any are. All other latched events are kept for [`poll_events`].
+    ///
+    /// A latched power loss is recovered from right away with [`Supervision::check_on_read`]
+    /// set. Otherwise it is remembered for the next [`check_power_loss`], whether or not [`Str`]
+    /// is cleared, as the latch may not survive the read.
+    ///
+    /// [`Str`]:  ir/enum.Target.html#variant.Str
+    /// [`poll_events`]: #method.poll_events
+    /// [`Supervision::check_on_read`]: ./struct.Supervision.html#structfield.check_on_read
+    /// [`check_power_loss`]: #method.check_power_loss
+    pub(crate) fn take_events(&mut self, consumed: Events) -> Result<Events, Error<SpiError>> {
+        let status = self.get_status()?;
+        self.take_from_status(&status, consumed)
+    }
+
+    /// Like [`take_events`](#method.take_events), for a status that has already been read.
+    pub(crate) fn take_from_status(&mut self, status: &Str, consumed: Events) -> Result<Events, Error<SpiError>> {
+        let events = Events::from_status(status);
+        self.pending_events |= events & !consumed;
+        let taken = events & consumed;
+        self.unrecovered_power_loss |= status.power_loss;
+        if self.supervision.check_on_read && status.power_loss {
+            // Recovery clears Str as well.
+            self.recover()?;
+        } else if !taken.is_empty() {
+            self.clear_status()?;
+        }
+        Ok(taken)
+    }
+
+    fn handle_status(&mut self, status: &Str) -> Result<bool, Error<SpiError>> {
+        self.pending_events |= Events::from_status(status);
+        if !status.power_loss && !self.unrecovered_power_loss {
+            return Ok(false);
+        }
+        self.recover()?;
+        Ok(true)
+    }
+
+    fn recover(&mut self) -> Result<(), Error<SpiError>> {
+        let registers = self.registers;
+        self.configure(registers.mdr0, registers.mdr1)?;
+        if self.supervision.restore_count {
+            // Goes through Dtr, which is rewritten below.
+            self.set_count(self.last_count)?;
+        }
+        self.write_dtr(registers.dtr)?;
+        self.clear_status()?;
+        self.unrecovered_power_loss = false;
+        self.pending_events |= Events::POWER_LOSS_RECOVERED;
+        Ok(())
+    }
+
+    fn read_count_register(&mut self, target: ir::Target) -> Result<i64, Error<SpiError>> {
+        let raw_result: &mut [u8] = &mut [0x00, 0x00, 0x00, 0x00];
+        let width = self.registers.width();
+        let raw_result = self.read_register(&mut raw_result[..width], target)?;
+        Ok(self.registers.decode_count(raw_result))
     }
 
 
@@ -283,21 +958,20 @@ impl<SPI, SpiError> Ls7366<SPI>
                 if data.len() > 1 {
                     Err(Error::PayloadTooBig)
                 } else {
-                    self.interface.write(&tx_buffer)?;
+                    self.interface.write(tx_buffer)?;
                     Ok(data)
                 }
             }
             Action::Read => {
-                let mut tx_buffer = [tx_buffer[0], 0x00, 0x00, 0x00, 0x00];
-                let result = self.interface.transfer(&mut tx_buffer)?;
-                data.copy_from_slice(result);
+                let tx_buffer = [tx_buffer[0], 0x00, 0x00, 0x00, 0x00];
+                self.interface.transfer(data, &tx_buffer)?;
                 Ok(data)
             }
             Action::Write => {
                 if data.len() > 5 {
                     Err(Error::PayloadTooBig)
                 } else {
-                    self.interface.write(&tx_buffer)?;
+                    self.interface.write(tx_buffer)?;
                     Ok(data)
                 }
             }
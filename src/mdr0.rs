@@ -19,7 +19,7 @@ use bitfield::bitfield;
 use crate::errors::EncoderError;
 use crate::traits::{Decodable, Encodable};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 /// Possible quadrature count modes
 pub enum QuadCountMode {
     NonQuad,
@@ -31,7 +31,7 @@ pub enum QuadCountMode {
     Quad4x,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 /// controls the behavior of the `Index` pin on the chip.
 pub enum IndexMode {
     /// disables input on the `index` pin.
@@ -44,7 +44,7 @@ pub enum IndexMode {
     LoadOtr,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 /// Enum representing cycle count modes.
 pub enum CycleCountMode {
     /// Free running count mode.
@@ -62,7 +62,7 @@ pub enum CycleCountMode {
     ModuloN,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 /// Controls Filter clock frequency, used to validate Index inputs.
 pub enum FilterClockDivisionFactor {
     /// Filter clock division factor = 1
@@ -71,7 +71,7 @@ pub enum FilterClockDivisionFactor {
     Two,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 /// Representation of the Mdr0 register.
 pub struct Mdr0 {
     /// Quadrature count mode
@@ -86,17 +86,56 @@ pub struct Mdr0 {
     pub filter_clock: FilterClockDivisionFactor,
 }
 
+impl Default for Mdr0 {
+    /// The chip's power-on configuration (all bits cleared).
+    fn default() -> Self {
+        Mdr0 {
+            quad_count_mode: QuadCountMode::NonQuad,
+            cycle_count_mode: CycleCountMode::FreeRunning,
+            index_mode: IndexMode::DisableIndex,
+            is_index_inverted: false,
+            filter_clock: FilterClockDivisionFactor::One,
+        }
+    }
+}
+
 
 bitfield! {
     struct Mdr0Payload(u8);
     impl Debug;
-    pub quad_count_mode, set_quad_count_mode: 2,0;
+    pub quad_count_mode, set_quad_count_mode: 1,0;
     pub cycle_count_mode, set_cycle_count_mode: 3,2;
     pub index_mode, set_index_mode: 5,4;
     pub is_index_inverted, set_is_index_inverted: 6;
     pub filter_clock_division_factor, set_filter_clock_division_factor: 7;
 }
 
+impl QuadCountMode {
+    /// Counts per encoder line, i.e. per quadrature cycle.
+    ///
+    /// In non-quadrature mode every clock pulse counts once, so a line is taken to be one pulse.
+    pub fn multiplier(&self) -> u32 {
+        match self {
+            QuadCountMode::NonQuad => 1,
+            QuadCountMode::Quad1x => 1,
+            QuadCountMode::Quad2x => 2,
+            QuadCountMode::Quad4x => 4,
+        }
+    }
+}
+
+impl CycleCountMode {
+    /// Whether the count is bounded by [`Dtr`], i.e. runs from 0 up to the value in [`Dtr`].
+    ///
+    /// [`Dtr`]: ../ir/enum.Target.html#variant.Dtr
+    pub fn is_bounded(&self) -> bool {
+        match self {
+            CycleCountMode::FreeRunning | CycleCountMode::SingleCycle => false,
+            CycleCountMode::RangeLimit | CycleCountMode::ModuloN => true,
+        }
+    }
+}
+
 impl Encodable for QuadCountMode {
     fn encode(&self) -> u8 {
         match self {
@@ -191,6 +230,7 @@ impl Encodable for Mdr0 {
         payload.set_quad_count_mode(quad_value);
         payload.set_cycle_count_mode(self.cycle_count_mode.encode());
         payload.set_index_mode(self.index_mode.encode());
+        payload.set_is_index_inverted(self.is_index_inverted);
         payload.set_filter_clock_division_factor(
             match self.filter_clock {
                 FilterClockDivisionFactor::One => { false }
@@ -8,7 +8,7 @@ use bitfield::bitfield;
 use crate::errors::EncoderError;
 use crate::traits::{Decodable, Encodable};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 /// Programmable size of the [`Cntr`] register.
 ///
 /// [`Cntr`]: ../ir/enum.Target.html#variant.Cntr
@@ -23,7 +23,7 @@ pub enum CounterMode {
     Byte1,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 /// Extended configuration options, mainly used for occurrence flags. (See datasheet).
 pub struct Mdr1 {
     /// programmed size of the counter([`Cntr`]) register.
@@ -39,6 +39,20 @@ pub struct Mdr1 {
     pub flag_on_cy: bool,
 }
 
+impl Default for Mdr1 {
+    /// The chip's power-on configuration (all bits cleared).
+    fn default() -> Self {
+        Mdr1 {
+            counter_mode: CounterMode::Byte4,
+            disable_counting: false,
+            flag_on_idx: false,
+            flag_on_cmp: false,
+            flag_on_bw: false,
+            flag_on_cy: false,
+        }
+    }
+}
+
 bitfield! {
     struct Payload(u8);
     impl Debug;
@@ -50,6 +64,25 @@ bitfield! {
     pub flag_on_bw, set_flag_on_bw: 6;
     pub flag_on_cy, set_flag_on_cy: 7;
 }
+impl CounterMode {
+    /// Number of bytes the [`Cntr`] register occupies in this mode.
+    ///
+    /// [`Cntr`]: ../ir/enum.Target.html#variant.Cntr
+    pub fn byte_count(&self) -> usize {
+        match self {
+            CounterMode::Byte4 => 4,
+            CounterMode::Byte3 => 3,
+            CounterMode::Byte2 => 2,
+            CounterMode::Byte1 => 1,
+        }
+    }
+
+    /// Largest unsigned value representable in this mode.
+    pub fn max_value(&self) -> u32 {
+        u32::MAX >> (32 - 8 * self.byte_count())
+    }
+}
+
 impl Encodable for CounterMode {
     fn encode(&self) -> u8 {
         match self {
@@ -0,0 +1,128 @@
+//! Time-coherent sampling of several chips sharing one index strobe.
+//!
+//! With the index pin configured as [`IndexMode::LoadOtr`], a pulse on the index input latches
+//! [`Cntr`] into [`Otr`]. Wiring the index inputs of several chips to one strobe line therefore
+//! captures all of their counts at the same instant, which can then be read out at leisure.
+//!
+//! [`SynchronizedAxes`] arms a set of drivers for this and collects the latched counts once every
+//! chip has reported the strobe through its index latch.
+//!
+//! [`IndexMode::LoadOtr`]: ../mdr0/enum.IndexMode.html#variant.LoadOtr
+//! [`Cntr`]: ../ir/enum.Target.html#variant.Cntr
+//! [`Otr`]: ../ir/enum.Target.html#variant.Otr
+//! [`SynchronizedAxes`]: ./struct.SynchronizedAxes.html
+
+use embedded_hal::spi::SpiDevice;
+
+use crate::events::Events;
+use crate::mdr0::IndexMode;
+use crate::{Error, Ls7366};
+
+/// An error together with the drivers it occurred with, handed back so they are not lost.
+pub type AxesError<SPI, SpiError, const N: usize> = (Error<SpiError>, [Ls7366<SPI>; N]);
+
+/// A set of `N` drivers whose index inputs share one strobe line.
+pub struct SynchronizedAxes<SPI, const N: usize> {
+    axes: [Ls7366<SPI>; N],
+    /// Index mode of each axis before it was armed, restored by [`release`].
+    ///
+    /// [`release`]: #method.release
+    previous_index_modes: [IndexMode; N],
+    /// Axes whose index latch has been observed since the last sample.
+    strobed: [bool; N],
+}
+
+impl<SPI, SpiError, const N: usize> SynchronizedAxes<SPI, N>
+    where SPI: SpiDevice<u8, Error=SpiError> {
+    /// Configures every axis to latch its count on the index strobe and discards stale index
+    /// latches.
+    ///
+    /// If an axis fails, the axes configured so far get their previous index mode back and the
+    /// drivers are returned along with the error.
+    pub fn new(mut axes: [Ls7366<SPI>; N]) -> Result<Self, AxesError<SPI, SpiError, N>> {
+        let mut previous_index_modes = [IndexMode::DisableIndex; N];
+        for (axis, previous) in axes.iter().zip(previous_index_modes.iter_mut()) {
+            *previous = axis.mdr0().index_mode;
+        }
+        for index in 0..N {
+            let axis = &mut axes[index];
+            if let Err(error) = axis.set_index_mode(IndexMode::LoadOtr).and_then(|()| axis.take_events(Events::INDEX)) {
+                // The original error is the one worth reporting.
+                for (axis, previous) in axes[..=index].iter_mut().zip(previous_index_modes.iter()) {
+                    let _ = axis.set_index_mode(*previous);
+                }
+                return Err((error, axes));
+            }
+        }
+        Ok(SynchronizedAxes {
+            axes,
+            previous_index_modes,
+            strobed: [false; N],
+        })
+    }
+
+    /// Checks the index latch of every axis that has not seen the strobe yet.
+    ///
+    /// Once every axis has been strobed, the latched counts are read out and returned in axis
+    /// order; otherwise `None` is returned and the next call resumes where this one left off.
+    ///
+    /// The counts are only coherent if they are read out before the next strobe arrives. Only the
+    /// index latch is consumed; other events stay queued for [`Ls7366::poll_events`].
+    ///
+    /// [`Ls7366::poll_events`]: ../struct.Ls7366.html#method.poll_events
+    pub fn poll(&mut self) -> Result<Option<[i64; N]>, Error<SpiError>> {
+        for (axis, strobed) in self.axes.iter_mut().zip(self.strobed.iter_mut()) {
+            if !*strobed {
+                *strobed = !axis.take_events(Events::INDEX)?.is_empty();
+            }
+        }
+        if !self.strobed.iter().all(|strobed| *strobed) {
+            return Ok(None);
+        }
+
+        let mut counts = [0; N];
+        for (axis, count) in self.axes.iter_mut().zip(counts.iter_mut()) {
+            *count = axis.read_latched_count()?;
+        }
+        self.strobed = [false; N];
+        Ok(Some(counts))
+    }
+
+    /// Blocks until every axis has been strobed, then returns the latched counts.
+    pub fn sample(&mut self) -> Result<[i64; N], Error<SpiError>> {
+        loop {
+            if let Some(counts) = self.poll()? {
+                return Ok(counts);
+            }
+        }
+    }
+
+    /// Gives access to a single axis, e.g. to change its configuration.
+    ///
+    /// Do not read the axis' live count between [`poll`] and [`sample`] calls while a sample is
+    /// pending: reading [`Cntr`] first loads it into [`Otr`], overwriting the count latched by
+    /// the strobe, so the next sample would no longer be coherent.
+    ///
+    /// [`poll`]: #method.poll
+    /// [`sample`]: #method.sample
+    /// [`Cntr`]: ../ir/enum.Target.html#variant.Cntr
+    /// [`Otr`]: ../ir/enum.Target.html#variant.Otr
+    pub fn axis(&mut self, index: usize) -> &mut Ls7366<SPI> {
+        &mut self.axes[index]
+    }
+
+    /// Restores each axis' previous index mode and hands the drivers back.
+    ///
+    /// Every axis is restored even if one fails; the drivers are then returned along with the
+    /// first error.
+    pub fn release(mut self) -> Result<[Ls7366<SPI>; N], AxesError<SPI, SpiError, N>> {
+        let mut result = Ok(());
+        for (axis, previous) in self.axes.iter_mut().zip(self.previous_index_modes.iter()) {
+            result = result.and(axis.set_index_mode(*previous));
+        }
+        match result {
+            Ok(()) => Ok(self.axes),
+            Err(error) => Err((error, self.axes)),
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! Driver-side copy of the chip's writable registers, shared by the blocking and async drivers.
+use crate::errors::EncoderError;
+use crate::ir::Target;
+use crate::mdr0::{CycleCountMode, FilterClockDivisionFactor, IndexMode, Mdr0, QuadCountMode};
+use crate::mdr1::{CounterMode, Mdr1};
+use crate::traits::Decodable;
+use crate::utilities;
+
+/// Primary configuration applied by the drivers' `new` constructors: 4x quadrature, free running.
+pub(crate) const INITIAL_MDR0: Mdr0 = Mdr0 {
+    quad_count_mode: QuadCountMode::Quad4x,
+    cycle_count_mode: CycleCountMode::FreeRunning,
+    index_mode: IndexMode::DisableIndex,
+    is_index_inverted: false,
+    filter_clock: FilterClockDivisionFactor::One,
+};
+
+/// Secondary configuration applied by the drivers' `new` constructors: full 4 byte counter.
+pub(crate) const INITIAL_MDR1: Mdr1 = Mdr1 {
+    counter_mode: CounterMode::Byte4,
+    disable_counting: false,
+    flag_on_idx: false,
+    flag_on_cmp: false,
+    flag_on_bw: false,
+    flag_on_cy: false,
+};
+
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Registers {
+    /// Last configuration written into `Mdr0`.
+    pub(crate) mdr0: Mdr0,
+    /// Last configuration written into `Mdr1`.
+    pub(crate) mdr1: Mdr1,
+    /// Last value written into `Dtr`.
+    pub(crate) dtr: u32,
+}
+
+impl Registers {
+    /// Returns the registers as they will be once `data` is written into `target`.
+    pub(crate) fn after_write(&self, target: &Target, data: &[u8]) -> Result<Self, EncoderError> {
+        let mut registers = *self;
+        match (target, data.first()) {
+            (Target::Mdr0, Some(raw)) => registers.mdr0 = Mdr0::decode(*raw)?,
+            (Target::Mdr1, Some(raw)) => registers.mdr1 = Mdr1::decode(*raw)?,
+            (Target::Dtr, Some(_)) => registers.dtr = utilities::vec_to_i64(data) as u32,
+            _ => {}
+        }
+        Ok(registers)
+    }
+
+    /// Number of bytes the counter, `Otr` and `Dtr` occupy in the configured counter mode.
+    pub(crate) fn width(&self) -> usize {
+        self.mdr1.counter_mode.byte_count()
+    }
+
+    /// Big-endian bytes of `value`, right aligned so the last `width()` bytes are to be written
+    /// into `Dtr`. `None` if the value does not fit into the configured width.
+    pub(crate) fn encode_dtr(&self, value: u32) -> Option<[u8; 4]> {
+        if value > self.mdr1.counter_mode.max_value() {
+            None
+        } else {
+            Some(value.to_be_bytes())
+        }
+    }
+
+    /// Raw representation of a count in the configured width: unsigned and at most `Dtr` if the
+    /// cycle count mode bounds the count by it, two's complement otherwise.
+    pub(crate) fn encode_count(&self, value: i64) -> Option<u32> {
+        if !self.mdr0.cycle_count_mode.is_bounded() {
+            utilities::i64_to_twos_complement(value, self.width())
+        } else if value >= 0 && value <= self.dtr as i64 {
+            Some(value as u32)
+        } else {
+            None
+        }
+    }
+
+    /// Count held in the raw big-endian bytes of `Cntr` or `Otr`, the inverse of
+    /// [`encode_count`](#method.encode_count).
+    pub(crate) fn decode_count(&self, raw: &[u8]) -> i64 {
+        if self.mdr0.cycle_count_mode.is_bounded() {
+            utilities::vec_to_i64(raw)
+        } else {
+            utilities::bytes_to_i64(raw)
+        }
+    }
+}
@@ -5,7 +5,7 @@ use bitfield::bitfield;
 use crate::errors::EncoderError;
 use crate::traits::Decodable;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 #[derive(PartialEq, Eq)]
 /// the sign of the counter's contents.
 pub enum SignBit {
@@ -27,7 +27,7 @@ bitfield! {
 }
 
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 #[derive(PartialEq, Eq)]
 /// Counting direction, corresponds to the motion of the attached encoder.
 pub enum Direction {
@@ -35,7 +35,7 @@ pub enum Direction {
     Down,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 /// Representation of the status register.
 pub struct Str {
     /// Carry (CNTR overflow) latch.
@@ -0,0 +1,80 @@
+#[cfg(test)]
+mod tests {
+    use crate::mdr0::{CycleCountMode, FilterClockDivisionFactor, IndexMode, Mdr0, QuadCountMode};
+    use crate::traits::{Decodable, Encodable};
+
+    const QUAD_MODES: [QuadCountMode; 4] = [
+        QuadCountMode::NonQuad,
+        QuadCountMode::Quad1x,
+        QuadCountMode::Quad2x,
+        QuadCountMode::Quad4x,
+    ];
+    const CYCLE_MODES: [CycleCountMode; 4] = [
+        CycleCountMode::FreeRunning,
+        CycleCountMode::SingleCycle,
+        CycleCountMode::RangeLimit,
+        CycleCountMode::ModuloN,
+    ];
+    const INDEX_MODES: [IndexMode; 4] = [
+        IndexMode::DisableIndex,
+        IndexMode::LoadCntr,
+        IndexMode::ClearCntr,
+        IndexMode::LoadOtr,
+    ];
+    const FILTER_CLOCKS: [FilterClockDivisionFactor; 2] = [
+        FilterClockDivisionFactor::One,
+        FilterClockDivisionFactor::Two,
+    ];
+
+    #[test]
+    fn test_mdr0_bit_layout() {
+        let mdr0 = Mdr0 {
+            quad_count_mode: QuadCountMode::Quad4x,
+            cycle_count_mode: CycleCountMode::ModuloN,
+            index_mode: IndexMode::LoadOtr,
+            is_index_inverted: true,
+            filter_clock: FilterClockDivisionFactor::Two,
+        };
+        assert_eq!(mdr0.encode(), 0b11111111);
+        assert_eq!(Mdr0 { quad_count_mode: QuadCountMode::NonQuad, ..mdr0 }.encode(), 0b11111100);
+        assert_eq!(Mdr0 { cycle_count_mode: CycleCountMode::FreeRunning, ..mdr0 }.encode(), 0b11110011);
+        assert_eq!(Mdr0 { index_mode: IndexMode::DisableIndex, ..mdr0 }.encode(), 0b11001111);
+        assert_eq!(Mdr0 { is_index_inverted: false, ..mdr0 }.encode(), 0b10111111);
+        assert_eq!(Mdr0 { filter_clock: FilterClockDivisionFactor::One, ..mdr0 }.encode(), 0b01111111);
+    }
+
+    #[test]
+    fn test_mdr0_decode_encode_all_bytes() {
+        for raw in 0..=u8::MAX {
+            let decoded = Mdr0::decode(raw).expect("failed decode");
+            assert_eq!(decoded.encode(), raw, "round trip failed for {:#010b}", raw);
+        }
+    }
+
+    #[test]
+    fn test_mdr0_encode_decode_all_fields() {
+        let mut seen = [false; 256];
+        for quad_count_mode in QUAD_MODES.iter() {
+            for cycle_count_mode in CYCLE_MODES.iter() {
+                for index_mode in INDEX_MODES.iter() {
+                    for is_index_inverted in [false, true].iter() {
+                        for filter_clock in FILTER_CLOCKS.iter() {
+                            let mdr0 = Mdr0 {
+                                quad_count_mode: *quad_count_mode,
+                                cycle_count_mode: *cycle_count_mode,
+                                index_mode: *index_mode,
+                                is_index_inverted: *is_index_inverted,
+                                filter_clock: *filter_clock,
+                            };
+                            let raw = mdr0.encode();
+                            assert!(!seen[raw as usize], "{:?} collides at {:#010b}", mdr0, raw);
+                            seen[raw as usize] = true;
+                            assert_eq!(Mdr0::decode(raw).expect("failed decode"), mdr0);
+                        }
+                    }
+                }
+            }
+        }
+        assert!(seen.iter().all(|seen| *seen));
+    }
+}
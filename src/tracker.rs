@@ -0,0 +1,106 @@
+//! Unbounded position tracking on top of the wrapping hardware counter.
+//!
+//! In free-running mode [`Cntr`] wraps around at the range of the configured [`CounterMode`],
+//! so with a narrow counter [`Ls7366::get_count`] only tells where the encoder is within the
+//! current wrap. [`PositionTracker`] accumulates the movement between successive reads into an
+//! `i64` position, which lets a 1-byte counter be read at a high SPI rate without losing the
+//! absolute position.
+//!
+//! Movement between reads is reconstructed from the difference of the raw counts, modulo the
+//! counter range, taking the shorter way round. Tracking is therefore exact as long as
+//! [`update`] is called at least twice per wrap period, i.e. the encoder moves by less than half
+//! the counter range between calls. When a longer move crosses the wrap point, the carry or
+//! borrow latch of [`Str`] tells which way the counter went and the move is still resolved.
+//! Only those two latches are consumed; other events stay queued for [`Ls7366::poll_events`].
+//!
+//! [`Cntr`]: ../ir/enum.Target.html#variant.Cntr
+//! [`Str`]: ../ir/enum.Target.html#variant.Str
+//! [`CounterMode`]: ../mdr1/enum.CounterMode.html
+//! [`Ls7366::get_count`]: ../struct.Ls7366.html#method.get_count
+//! [`Ls7366::poll_events`]: ../struct.Ls7366.html#method.poll_events
+//! [`PositionTracker`]: ./struct.PositionTracker.html
+//! [`update`]: ./struct.PositionTracker.html#method.update
+
+use embedded_hal::spi::SpiDevice;
+
+use crate::events::Events;
+use crate::mdr0::CycleCountMode;
+use crate::{utilities, Error, Ls7366};
+
+/// A driver whose count is extended to a full `i64` position.
+///
+/// The counter must be free-running; the other cycle modes do not wrap the way this expects.
+pub struct PositionTracker<SPI> {
+    driver: Ls7366<SPI>,
+    /// Accumulated position.
+    position: i64,
+    /// Count read by the last update.
+    last_count: i64,
+}
+
+impl<SPI, SpiError> PositionTracker<SPI>
+    where SPI: SpiDevice<u8, Error=SpiError> {
+    /// Starts tracking from the chip's current count, which becomes the initial position.
+    ///
+    /// Returns [`Error::UnsupportedCycleCountMode`] unless the cached configuration is
+    /// [`CycleCountMode::FreeRunning`].
+    ///
+    /// [`Error::UnsupportedCycleCountMode`]: ../enum.Error.html#variant.UnsupportedCycleCountMode
+    /// [`CycleCountMode::FreeRunning`]: ../mdr0/enum.CycleCountMode.html#variant.FreeRunning
+    pub fn new(mut driver: Ls7366<SPI>) -> Result<Self, Error<SpiError>> {
+        if driver.mdr0().cycle_count_mode != CycleCountMode::FreeRunning {
+            return Err(Error::UnsupportedCycleCountMode);
+        }
+        let snapshot = driver.snapshot()?;
+        // Discard stale carry and borrow latches.
+        driver.take_from_status(&snapshot.status, Events::CARRY | Events::BORROW)?;
+        Ok(PositionTracker {
+            driver,
+            position: snapshot.count,
+            last_count: snapshot.count,
+        })
+    }
+
+    /// Reads the chip and returns the updated position.
+    pub fn update(&mut self) -> Result<i64, Error<SpiError>> {
+        let snapshot = self.driver.snapshot()?;
+        self.driver.take_from_status(&snapshot.status, Events::CARRY | Events::BORROW)?;
+
+        let width = self.driver.mdr1().counter_mode.byte_count();
+        let range: i64 = 1 << (width * 8);
+        let forward = utilities::forward_distance(self.last_count, snapshot.count, width);
+        let delta = match (snapshot.status.cary, snapshot.status.borrow) {
+            _ if forward == 0 => 0,
+            (true, false) => forward,
+            (false, true) => forward - range,
+            _ => utilities::shortest_distance(self.last_count, snapshot.count, width),
+        };
+
+        self.last_count = snapshot.count;
+        self.position += delta;
+        Ok(self.position)
+    }
+
+    /// Position as of the last update.
+    pub fn position(&self) -> i64 {
+        self.position
+    }
+
+    /// Redefines the current position without touching the chip.
+    pub fn set_position(&mut self, position: i64) {
+        self.position = position;
+    }
+
+    /// Gives access to the underlying driver.
+    ///
+    /// Changing the count or the counter width through it breaks tracking; release the driver
+    /// and start a new tracker instead.
+    pub fn driver(&mut self) -> &mut Ls7366<SPI> {
+        &mut self.driver
+    }
+
+    /// Hands the driver back.
+    pub fn release(self) -> Ls7366<SPI> {
+        self.driver
+    }
+}
@@ -9,7 +9,6 @@ pub trait Encodable {
 /// Unsuccessful decodes result in an ([`EncoderError`])
 ///
 /// [`EncoderError`]: ../errors/enum.EncoderError.html
-
 pub trait Decodable: Sized{
     fn decode(raw:u8) -> Result<Self, EncoderError>;
 }
\ No newline at end of file
@@ -1,20 +1,92 @@
-/// Converts a vector of length 4 into a u32
+/// Converts a big-endian slice of up to 4 bytes into its unsigned value.
 pub(crate) fn vec_to_i64(data: &[u8]) -> i64 {
     if data.len()>4{
         panic!("payload too big!, got {:?}", data)
     }
     let mut result: i64 = 0x00;
-    let mut i = 0;
-    for byte in data.iter().rev() {
+    for (i, byte) in data.iter().rev().enumerate() {
         let converted_byte: i64 = *byte as i64;
 
-        result += converted_byte << i * 8;
-        i += 1;
+        result += converted_byte << (i * 8);
     }
     result
 }
+
+/// Converts a big-endian slice of up to 4 bytes into a signed value, treating the slice as a
+/// two's complement number exactly `data.len()` bytes wide.
+pub(crate) fn bytes_to_i64(data: &[u8]) -> i64 {
+    if data.is_empty() {
+        return 0;
+    }
+    let sign: i64 = 1 << (data.len() * 8 - 1);
+    (vec_to_i64(data) ^ sign) - sign
+}
+
+/// Converts a signed value into its two's complement representation `width` bytes wide,
+/// or `None` if it does not fit into that width.
+pub(crate) fn i64_to_twos_complement(value: i64, width: usize) -> Option<u32> {
+    let bits = width * 8;
+    let limit: i64 = 1 << (bits - 1);
+    if value < -limit || value >= limit {
+        return None;
+    }
+    Some(value as u32 & (u32::MAX >> (32 - bits)))
+}
+
+/// Distance counted upwards from `from` to `to` by a counter `width` bytes wide, which wraps
+/// around at its range. Both counts may be given raw or sign-extended.
+pub(crate) fn forward_distance(from: i64, to: i64, width: usize) -> i64 {
+    let range: i64 = 1 << (width * 8);
+    (to - from).rem_euclid(range)
+}
+
+/// Signed distance from `from` to `to` by a counter `width` bytes wide, taking the shorter way
+/// around the wrap point.
+pub(crate) fn shortest_distance(from: i64, to: i64, width: usize) -> i64 {
+    let range: i64 = 1 << (width * 8);
+    let forward = forward_distance(from, to, width);
+    if forward < range / 2 { forward } else { forward - range }
+}
+
 #[test]
+#[allow(clippy::useless_vec)]
 fn test_vec_to_u32(){
     assert_eq!(vec_to_i64(&vec![0xDE, 0xAD, 0xBE, 0xEF]), 0xDEADBEEF);
     assert_eq!(vec_to_i64(&vec![0x00, 0x00, 0x00, 0x0]), 0x0000000)
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_bytes_to_i64(){
+    assert_eq!(bytes_to_i64(&[0xDE, 0xAD, 0xBE, 0xEF]), 0xDEADBEEFu32 as i32 as i64);
+    assert_eq!(bytes_to_i64(&[0x7F, 0xFF, 0xFF, 0xFF]), i32::MAX as i64);
+    assert_eq!(bytes_to_i64(&[0x80, 0x00, 0x00]), -0x800000);
+    assert_eq!(bytes_to_i64(&[0x7F, 0xFF, 0xFF]), 0x7FFFFF);
+    assert_eq!(bytes_to_i64(&[0xFF, 0xFE]), -2);
+    assert_eq!(bytes_to_i64(&[0x80]), -128);
+    assert_eq!(bytes_to_i64(&[0x7F]), 127);
+    assert_eq!(bytes_to_i64(&[]), 0);
+}
+
+#[test]
+fn test_i64_to_twos_complement(){
+    assert_eq!(i64_to_twos_complement(-1, 4), Some(0xFFFFFFFF));
+    assert_eq!(i64_to_twos_complement(i32::MIN as i64, 4), Some(0x80000000));
+    assert_eq!(i64_to_twos_complement(i32::MAX as i64 + 1, 4), None);
+    assert_eq!(i64_to_twos_complement(-2, 2), Some(0xFFFE));
+    assert_eq!(i64_to_twos_complement(32767, 2), Some(0x7FFF));
+    assert_eq!(i64_to_twos_complement(32768, 2), None);
+    assert_eq!(i64_to_twos_complement(-128, 1), Some(0x80));
+    assert_eq!(i64_to_twos_complement(-129, 1), None);
+}
+
+#[test]
+fn test_wrapping_distances(){
+    assert_eq!(forward_distance(250, 4, 1), 10);
+    assert_eq!(forward_distance(-6, 4, 1), 10);
+    assert_eq!(forward_distance(4, 250, 1), 246);
+    assert_eq!(shortest_distance(4, 250, 1), -10);
+    assert_eq!(shortest_distance(-6, 4, 1), 10);
+    assert_eq!(shortest_distance(0, 127, 1), 127);
+    assert_eq!(shortest_distance(0, 128, 1), -128);
+    assert_eq!(shortest_distance(i32::MAX as i64, i32::MIN as i64, 4), 1);
+}
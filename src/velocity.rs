@@ -0,0 +1,310 @@
+//! Velocity and acceleration estimation from timestamped counts.
+//!
+//! The caller samples the count (e.g. with [`Ls7366::get_count`]) and supplies the time of each
+//! sample in microseconds from any monotonic clock, which keeps this module free of any timing
+//! dependency. [`MotionEstimator`] undoes the counter wrap of the configured [`CounterMode`] and
+//! hands the unwrapped positions to one of the [`Estimator`]s:
+//! - [`FiniteDifference`] differentiates the last samples directly. It has no lag but passes
+//!   quantisation noise straight through.
+//! - [`LeastSquares`] fits a parabola through the last `N` samples, trading lag for smoothness.
+//! - [`AlphaBeta`] is a tracking filter whose gains set the same trade-off without keeping a
+//!   sample history.
+//!
+//! ```
+//! use ls7366::mdr1::CounterMode;
+//! use ls7366::velocity::{LeastSquares, MotionEstimator};
+//!
+//! let mut estimator = MotionEstimator::new(LeastSquares::<8>::new(), CounterMode::Byte1);
+//! let mut motion = None;
+//! for sample in 0..20i64 {
+//!     // 1-byte counts, moving 50 counts per millisecond and wrapping every few samples.
+//!     let count = (sample * 50) as i8 as i64;
+//!     motion = estimator.update(sample as u64 * 1000, count);
+//! }
+//! let motion = motion.unwrap();
+//! assert_eq!(motion.position, 950);
+//! assert!((motion.velocity - 50_000.0).abs() < 1.0);
+//! ```
+//!
+//! [`Ls7366::get_count`]: ../struct.Ls7366.html#method.get_count
+//! [`CounterMode`]: ../mdr1/enum.CounterMode.html
+//! [`MotionEstimator`]: ./struct.MotionEstimator.html
+//! [`Estimator`]: ./trait.Estimator.html
+//! [`FiniteDifference`]: ./struct.FiniteDifference.html
+//! [`LeastSquares`]: ./struct.LeastSquares.html
+//! [`AlphaBeta`]: ./struct.AlphaBeta.html
+
+use crate::mdr1::CounterMode;
+use crate::utilities;
+
+const MICROSECONDS: f32 = 1e-6;
+
+/// Estimated state of motion, in counts and seconds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Motion {
+    /// Position with the counter wrap undone.
+    pub position: i64,
+    /// Counts per second.
+    pub velocity: f32,
+    /// Counts per second squared.
+    pub acceleration: f32,
+}
+
+/// An estimation method, fed with unwrapped positions and strictly increasing timestamps.
+pub trait Estimator {
+    /// Takes a new sample and returns the estimate it leads to, or `None` while there are not
+    /// enough samples yet.
+    ///
+    /// A sample not newer than the previous one is rejected: `None` is returned and the state
+    /// is left unchanged, as a zero or negative interval has no meaningful derivative.
+    fn update(&mut self, timestamp_us: u64, position: i64) -> Option<Motion>;
+
+    /// Forgets all samples.
+    fn reset(&mut self);
+}
+
+/// Feeds raw counts into an [`Estimator`], undoing the counter wrap.
+///
+/// Like [`PositionTracker`], this relies on the counter moving by less than half its range
+/// between samples.
+///
+/// [`Estimator`]: ./trait.Estimator.html
+/// [`PositionTracker`]: ../tracker/struct.PositionTracker.html
+pub struct MotionEstimator<E> {
+    estimator: E,
+    counter_mode: CounterMode,
+    /// Timestamp, count and unwrapped position of the last accepted sample.
+    last: Option<(u64, i64, i64)>,
+    motion: Option<Motion>,
+}
+
+impl<E: Estimator> MotionEstimator<E> {
+    /// Creates an estimator for counts read with `counter_mode`, usually taken from
+    /// [`Ls7366::mdr1`].
+    ///
+    /// [`Ls7366::mdr1`]: ../struct.Ls7366.html#method.mdr1
+    pub fn new(estimator: E, counter_mode: CounterMode) -> Self {
+        MotionEstimator {
+            estimator,
+            counter_mode,
+            last: None,
+            motion: None,
+        }
+    }
+
+    /// Takes a count sampled at `timestamp_us` and returns the updated estimate.
+    ///
+    /// Samples not newer than the previous one are ignored.
+    pub fn update(&mut self, timestamp_us: u64, count: i64) -> Option<Motion> {
+        let position = match self.last {
+            None => count,
+            Some((last_timestamp, _, _)) if timestamp_us <= last_timestamp => return self.motion,
+            Some((_, last_count, last_position)) => {
+                last_position + utilities::shortest_distance(last_count, count, self.counter_mode.byte_count())
+            }
+        };
+        self.last = Some((timestamp_us, count, position));
+        self.motion = self.estimator.update(timestamp_us, position);
+        self.motion
+    }
+
+    /// The last estimate.
+    pub fn motion(&self) -> Option<Motion> {
+        self.motion
+    }
+
+    /// Forgets all samples, e.g. after the count was changed.
+    pub fn reset(&mut self) {
+        self.last = None;
+        self.motion = None;
+        self.estimator.reset();
+    }
+
+    /// Hands the estimator back.
+    pub fn release(self) -> E {
+        self.estimator
+    }
+}
+
+/// Seconds from `earlier` to `later`.
+fn seconds(earlier: u64, later: u64) -> f32 {
+    (later - earlier) as f32 * MICROSECONDS
+}
+
+/// Velocity from the last two samples, acceleration from the last two velocities.
+#[derive(Clone, Debug, Default)]
+pub struct FiniteDifference {
+    /// Last sample.
+    last: Option<(u64, i64)>,
+    /// Last velocity and the midpoint of the interval it was measured over.
+    last_velocity: Option<(f32, f32)>,
+}
+
+impl FiniteDifference {
+    pub fn new() -> Self {
+        FiniteDifference::default()
+    }
+}
+
+impl Estimator for FiniteDifference {
+    fn update(&mut self, timestamp_us: u64, position: i64) -> Option<Motion> {
+        if matches!(self.last, Some((last_timestamp, _)) if timestamp_us <= last_timestamp) {
+            return None;
+        }
+        let (last_timestamp, last_position) = self.last.replace((timestamp_us, position))?;
+        let interval = seconds(last_timestamp, timestamp_us);
+        let velocity = (position - last_position) as f32 / interval;
+
+        // Midpoints are measured from the previous sample, so only intervals matter.
+        let acceleration = match self.last_velocity {
+            Some((last_velocity, last_half_interval)) => {
+                (velocity - last_velocity) / (last_half_interval + interval / 2.0)
+            }
+            None => 0.0,
+        };
+        self.last_velocity = Some((velocity, interval / 2.0));
+        Some(Motion { position, velocity, acceleration })
+    }
+
+    fn reset(&mut self) {
+        *self = FiniteDifference::default();
+    }
+}
+
+/// Least-squares fit of a parabola through the last `N` samples, differentiated at the newest.
+///
+/// With only two samples in the window a straight line is fitted and the acceleration is zero.
+#[derive(Clone, Debug)]
+pub struct LeastSquares<const N: usize> {
+    /// Ring buffer of samples, `len` of them valid, the newest at `head`.
+    samples: [(u64, i64); N],
+    len: usize,
+    head: usize,
+}
+
+impl<const N: usize> Default for LeastSquares<N> {
+    fn default() -> Self {
+        LeastSquares::new()
+    }
+}
+
+impl<const N: usize> LeastSquares<N> {
+    pub fn new() -> Self {
+        LeastSquares { samples: [(0, 0); N], len: 0, head: 0 }
+    }
+
+    /// Samples in the window, newest first.
+    fn window(&self) -> impl Iterator<Item = &(u64, i64)> {
+        (0..self.len).map(move |age| &self.samples[(self.head + N - age) % N])
+    }
+}
+
+impl<const N: usize> Estimator for LeastSquares<N> {
+    fn update(&mut self, timestamp_us: u64, position: i64) -> Option<Motion> {
+        if N == 0 || (self.len > 0 && timestamp_us <= self.samples[self.head].0) {
+            return None;
+        }
+        self.head = (self.head + 1) % N;
+        self.samples[self.head] = (timestamp_us, position);
+        self.len = (self.len + 1).min(N);
+        if self.len < 2 {
+            return None;
+        }
+
+        // Fit in time scaled to the window span, relative to the newest sample, so the sums
+        // stay well conditioned regardless of sample rate.
+        let oldest = self.window().last().map_or(timestamp_us, |sample| sample.0);
+        let span = seconds(oldest, timestamp_us);
+        let mut sums = [0.0f32; 5];
+        let mut weighted = [0.0f32; 3];
+        for &(timestamp, sample) in self.window() {
+            let time = -seconds(timestamp, timestamp_us) / span;
+            let offset = (sample - position) as f32;
+            let mut power = 1.0;
+            for (k, sum) in sums.iter_mut().enumerate() {
+                *sum += power;
+                if k < 3 {
+                    weighted[k] += offset * power;
+                }
+                power *= time;
+            }
+        }
+
+        let [s0, s1, s2, s3, s4] = sums;
+        let [t0, t1, t2] = weighted;
+        let quadratic = s0 * (s2 * s4 - s3 * s3) - s1 * (s1 * s4 - s2 * s3) + s2 * (s1 * s3 - s2 * s2);
+        let (slope, curvature) = if self.len >= 3 && quadratic.abs() > f32::EPSILON {
+            // Cramer's rule on the normal equations for the linear and quadratic terms.
+            let slope = s0 * (t1 * s4 - s3 * t2) - t0 * (s1 * s4 - s2 * s3) + s2 * (s1 * t2 - t1 * s2);
+            let curvature = s0 * (s2 * t2 - t1 * s3) - s1 * (s1 * t2 - t1 * s2) + t0 * (s1 * s3 - s2 * s2);
+            (slope / quadratic, curvature / quadratic)
+        } else {
+            ((s0 * t1 - s1 * t0) / (s0 * s2 - s1 * s1), 0.0)
+        };
+        Some(Motion {
+            position,
+            velocity: slope / span,
+            acceleration: 2.0 * curvature / (span * span),
+        })
+    }
+
+    fn reset(&mut self) {
+        self.len = 0;
+    }
+}
+
+/// Alpha-beta tracking filter.
+///
+/// `alpha` weighs the measured position against the predicted one and `beta` corrects the
+/// velocity from the same residual; both lie in `0.0..=1.0`, smaller values smoothing more.
+/// The acceleration is the change of the filtered velocity between samples.
+#[derive(Clone, Debug)]
+pub struct AlphaBeta {
+    alpha: f32,
+    beta: f32,
+    /// Timestamp, filtered position and filtered velocity.
+    state: Option<(u64, f32, f32)>,
+    /// Base of the filtered position, keeping the `f32` state small.
+    origin: i64,
+}
+
+impl AlphaBeta {
+    pub fn new(alpha: f32, beta: f32) -> Self {
+        AlphaBeta { alpha, beta, state: None, origin: 0 }
+    }
+}
+
+impl Estimator for AlphaBeta {
+    fn update(&mut self, timestamp_us: u64, position: i64) -> Option<Motion> {
+        let (last_timestamp, last_position, last_velocity) = match self.state {
+            Some((last_timestamp, _, _)) if timestamp_us <= last_timestamp => return None,
+            Some(state) => state,
+            None => {
+                self.origin = position;
+                self.state = Some((timestamp_us, 0.0, 0.0));
+                return None;
+            }
+        };
+        let interval = seconds(last_timestamp, timestamp_us);
+        let predicted = last_position + last_velocity * interval;
+        let residual = (position - self.origin) as f32 - predicted;
+        let mut filtered = predicted + self.alpha * residual;
+        let velocity = last_velocity + self.beta * residual / interval;
+
+        // Move the origin along so the filtered position never loses precision.
+        let whole = filtered as i64;
+        self.origin += whole;
+        filtered -= whole as f32;
+        self.state = Some((timestamp_us, filtered, velocity));
+
+        Some(Motion {
+            position,
+            velocity,
+            acceleration: (velocity - last_velocity) / interval,
+        })
+    }
+
+    fn reset(&mut self) {
+        self.state = None;
+    }
+}
@@ -0,0 +1,297 @@
+#![cfg(feature = "async")]
+
+#[cfg(test)]
+mod tests {
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+
+    use embedded_hal_mock::eh1::spi::{Mock, Transaction};
+
+    use ls7366::{Action, Encodable, Target};
+    use ls7366::asynch::Ls7366Async;
+    use ls7366::events::Events;
+    use ls7366::ir::InstructionRegister;
+    use ls7366::mdr0::{CycleCountMode, FilterClockDivisionFactor, IndexMode, Mdr0, QuadCountMode};
+    use ls7366::mdr1::{CounterMode, Mdr1};
+    use ls7366::str_register;
+    use ls7366::Error;
+
+    type SpiTransaction = Transaction<u8>;
+
+    /// Drives a future to completion; the mock SPI never actually suspends.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let mut context = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+                return output;
+            }
+        }
+    }
+
+    fn ir(target: Target, action: Action) -> u8 {
+        InstructionRegister { target, action }.encode()
+    }
+
+    fn write(data: Vec<u8>) -> Vec<SpiTransaction> {
+        vec![
+            SpiTransaction::transaction_start(),
+            SpiTransaction::write_vec(data),
+            SpiTransaction::transaction_end(),
+        ]
+    }
+
+    fn read(target: Target, response: Vec<u8>) -> Vec<SpiTransaction> {
+        vec![
+            SpiTransaction::transaction_start(),
+            SpiTransaction::write_vec(vec![ir(target, Action::Read)]),
+            SpiTransaction::read_vec(response),
+            SpiTransaction::transaction_end(),
+        ]
+    }
+
+    fn read_str(response: u8) -> Vec<SpiTransaction> {
+        read(Target::Str, vec![0x00, 0x00, 0x00, response])
+    }
+
+    #[test]
+    fn test_new() {
+        let expectations = [
+            write(vec![ir(Target::Mdr0, Action::Write), 0b00000011]),
+            write(vec![ir(Target::Mdr1, Action::Write), 0x00]),
+            write(vec![ir(Target::Dtr, Action::Write), 0x00, 0x00, 0x00, 0x00]),
+            write(vec![ir(Target::Cntr, Action::Load)]),
+            write(vec![ir(Target::Str, Action::Clear)]),
+        ].concat();
+        let mut spi = Mock::new(&expectations);
+
+        let driver = block_on(Ls7366Async::new(spi.clone())).unwrap();
+        assert_eq!(driver.mdr1().counter_mode, CounterMode::Byte4);
+        spi.done();
+    }
+
+    #[test]
+    fn test_get_count() {
+        let expectations = [
+            read(Target::Cntr, vec![0xFF, 0xFF, 0xFF, 0xFE]),
+            write(vec![ir(Target::Mdr1, Action::Write), 0b00000010]),
+            read(Target::Cntr, vec![0x80, 0x00]),
+        ].concat();
+        let mut spi = Mock::new(&expectations);
+        let mut driver = Ls7366Async::new_uninit(spi.clone());
+
+        block_on(async {
+            assert_eq!(driver.get_count().await.unwrap(), -2);
+            driver.set_counter_mode(CounterMode::Byte2).await.unwrap();
+            assert_eq!(driver.get_count().await.unwrap(), -32768);
+        });
+        spi.done();
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let expectations = [
+            write(vec![ir(Target::Otr, Action::Load)]),
+            read(Target::Str, vec![0x00, 0x00, 0x00, 0b00001011]),
+            read(Target::Otr, vec![0xFF, 0xFF, 0xFF, 0x9C]),
+        ].concat();
+        let mut spi = Mock::new(&expectations);
+        let mut driver = Ls7366Async::new_uninit(spi.clone());
+
+        let snapshot = block_on(driver.snapshot()).unwrap();
+        assert_eq!(snapshot.count, -100);
+        assert_eq!(snapshot.status.sign_bit, str_register::SignBit::Negative);
+        assert_eq!(snapshot.status.count_direction, str_register::Direction::Up);
+        spi.done();
+    }
+
+    #[test]
+    fn test_write_register() {
+        let expectations = [
+            write(vec![ir(Target::Dtr, Action::Write), 0xBA, 0xAD, 0xBE, 0xEF]),
+            write(vec![ir(Target::Mdr0, Action::Write), 0b00100110]),
+        ].concat();
+        let mut spi = Mock::new(&expectations);
+        let mut driver = Ls7366Async::new_uninit(spi.clone());
+
+        block_on(async {
+            driver.write_register(Target::Dtr, &[0xBA, 0xAD, 0xBE, 0xEF]).await.unwrap();
+            assert_eq!(driver.read_dtr(), 0xBAADBEEF);
+            driver.write_register(Target::Mdr0, &[0b00100110]).await.unwrap();
+            assert_eq!(driver.mdr0(), Mdr0 {
+                quad_count_mode: QuadCountMode::Quad2x,
+                cycle_count_mode: CycleCountMode::SingleCycle,
+                index_mode: IndexMode::ClearCntr,
+                is_index_inverted: false,
+                filter_clock: FilterClockDivisionFactor::One,
+            });
+            match driver.write_register(Target::Dtr, &[0x00; 5]).await {
+                Err(Error::PayloadTooBig) => {}
+                other => panic!("expected PayloadTooBig, got {:?}", other),
+            }
+        });
+        spi.done();
+    }
+
+    #[test]
+    fn test_verify_configuration() {
+        let expectations = [
+            read(Target::Mdr0, vec![0x00]),
+            read(Target::Mdr1, vec![0x00]),
+            // floating MISO reads back as all ones.
+            read(Target::Mdr0, vec![0xFF]),
+            read(Target::Mdr1, vec![0xFF]),
+        ].concat();
+        let mut spi = Mock::new(&expectations);
+        let mut driver = Ls7366Async::new_uninit(spi.clone());
+
+        block_on(async {
+            driver.verify_configuration().await.unwrap();
+            match driver.verify_configuration().await {
+                Err(Error::ConfigurationMismatch(mismatch)) => {
+                    assert_eq!(mismatch.expected_mdr0, Mdr0::default());
+                    assert_eq!(mismatch.actual_mdr0.quad_count_mode, QuadCountMode::Quad4x);
+                    assert_eq!(mismatch.expected_mdr1, Mdr1::default());
+                    assert_eq!(mismatch.actual_mdr1.counter_mode, CounterMode::Byte1);
+                }
+                other => panic!("expected a configuration mismatch, got {:?}", other),
+            }
+        });
+        spi.done();
+    }
+
+    #[test]
+    fn test_write_dtr() {
+        let expectations = [
+            write(vec![ir(Target::Dtr, Action::Write), 0xDE, 0xAD, 0xBE, 0xEF]),
+            write(vec![ir(Target::Mdr1, Action::Write), 0b00000011]),
+            write(vec![ir(Target::Dtr, Action::Write), 0x2A]),
+        ].concat();
+        let mut spi = Mock::new(&expectations);
+        let mut driver = Ls7366Async::new_uninit(spi.clone());
+
+        block_on(async {
+            driver.write_dtr(0xDEADBEEF).await.unwrap();
+            assert_eq!(driver.read_dtr(), 0xDEADBEEF);
+            driver.set_counter_mode(CounterMode::Byte1).await.unwrap();
+            driver.write_dtr(42).await.unwrap();
+            match driver.write_dtr(0x100).await {
+                Err(Error::ValueOutOfRange) => {}
+                other => panic!("expected ValueOutOfRange, got {:?}", other),
+            }
+            assert_eq!(driver.read_dtr(), 42);
+        });
+        spi.done();
+    }
+
+    #[test]
+    fn test_set_count() {
+        let expectations = [
+            write(vec![ir(Target::Dtr, Action::Write), 0x00, 0x00, 0x01, 0x00]),
+            write(vec![ir(Target::Cntr, Action::Load)]),
+            write(vec![ir(Target::Mdr1, Action::Write), 0b00000010]),
+            write(vec![ir(Target::Dtr, Action::Write), 0xFF, 0xFE]),
+            write(vec![ir(Target::Cntr, Action::Load)]),
+        ].concat();
+        let mut spi = Mock::new(&expectations);
+        let mut driver = Ls7366Async::new_uninit(spi.clone());
+
+        block_on(async {
+            driver.set_count(256).await.unwrap();
+            driver.set_counter_mode(CounterMode::Byte2).await.unwrap();
+            driver.set_count(-2).await.unwrap();
+            assert_eq!(driver.read_dtr(), 0xFFFE);
+            match driver.set_count(32768).await {
+                Err(Error::ValueOutOfRange) => {}
+                other => panic!("expected ValueOutOfRange, got {:?}", other),
+            }
+        });
+        spi.done();
+    }
+
+    #[test]
+    fn test_configure_range_limit_and_modulo() {
+        let expectations = [
+            write(vec![ir(Target::Dtr, Action::Write), 0x00, 0x00, 0x00, 0xFA]),
+            write(vec![ir(Target::Mdr0, Action::Write), 0b00001000]),
+            // The limit in Dtr is restored after loading the count.
+            write(vec![ir(Target::Dtr, Action::Write), 0x00, 0x00, 0x00, 0x64]),
+            write(vec![ir(Target::Cntr, Action::Load)]),
+            write(vec![ir(Target::Dtr, Action::Write), 0x00, 0x00, 0x00, 0xFA]),
+            // Counts are unsigned.
+            read(Target::Cntr, vec![0x80, 0x00, 0x00, 0x00]),
+            write(vec![ir(Target::Dtr, Action::Write), 0x00, 0x00, 0x01, 0x67]),
+            write(vec![ir(Target::Mdr0, Action::Write), 0b00001100]),
+        ].concat();
+        let mut spi = Mock::new(&expectations);
+        let mut driver = Ls7366Async::new_uninit(spi.clone());
+
+        block_on(async {
+            driver.configure_range_limit(250).await.unwrap();
+            assert_eq!(driver.mdr0().cycle_count_mode, CycleCountMode::RangeLimit);
+            driver.set_count(100).await.unwrap();
+            assert_eq!(driver.read_dtr(), 250);
+            match driver.set_count(251).await {
+                Err(Error::ValueOutOfRange) => {}
+                other => panic!("expected ValueOutOfRange, got {:?}", other),
+            }
+            assert_eq!(driver.get_count().await.unwrap(), 0x8000_0000);
+
+            match driver.configure_modulo(0).await {
+                Err(Error::ValueOutOfRange) => {}
+                other => panic!("expected ValueOutOfRange, got {:?}", other),
+            }
+            driver.configure_modulo(360).await.unwrap();
+            assert_eq!(driver.mdr0().cycle_count_mode, CycleCountMode::ModuloN);
+            assert_eq!(driver.read_dtr(), 359);
+        });
+        spi.done();
+    }
+
+    #[test]
+    fn test_poll_events() {
+        let expectations = [
+            read_str(0b10111011),
+            write(vec![ir(Target::Str, Action::Clear)]),
+            // Nothing latched, so nothing to clear.
+            read_str(0b00001000),
+        ].concat();
+        let mut spi = Mock::new(&expectations);
+        let mut driver = Ls7366Async::new_uninit(spi.clone());
+
+        block_on(async {
+            let events = driver.poll_events().await.unwrap();
+            assert_eq!(events, Events::CARRY | Events::COMPARE | Events::INDEX);
+            assert!(driver.poll_events().await.unwrap().is_empty());
+        });
+        spi.done();
+    }
+
+    #[test]
+    fn test_act() {
+        let load = || InstructionRegister { target: Target::Otr, action: Action::Load };
+        let read_cntr = || InstructionRegister { target: Target::Cntr, action: Action::Read };
+        let expectations = [
+            write(vec![load().encode()]),
+            vec![
+                SpiTransaction::transaction_start(),
+                SpiTransaction::transfer(vec![read_cntr().encode(), 0x00, 0x00, 0x00, 0x00], vec![0x00, 0x12, 0x34, 0x56, 0x78]),
+                SpiTransaction::transaction_end(),
+            ],
+        ].concat();
+        let mut spi = Mock::new(&expectations);
+        let mut driver = Ls7366Async::new_uninit(spi.clone());
+
+        block_on(async {
+            driver.act(load(), &mut [0x00]).await.unwrap();
+            let data = &mut [0x00; 5];
+            assert_eq!(driver.act(read_cntr(), data).await.unwrap(), [0x00, 0x12, 0x34, 0x56, 0x78]);
+            match driver.act(load(), &mut [0x00, 0x00]).await {
+                Err(Error::PayloadTooBig) => {}
+                other => panic!("expected PayloadTooBig, got {:?}", other),
+            }
+        });
+        spi.done();
+    }
+}
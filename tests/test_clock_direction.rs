@@ -0,0 +1,105 @@
+#[cfg(test)]
+mod tests {
+    use core::cell::RefCell;
+
+    use ls7366::clock_direction::{PulseRate, MDR0, MDR1};
+    use ls7366::emulator::{EmulatorDevice, Inputs, Ls7366Emulator};
+    use ls7366::generator::{ConstantVelocity, QuadratureGenerator, Signalling};
+    use ls7366::Ls7366;
+    use ls7366::mdr1::{CounterMode, Mdr1};
+    use ls7366::str_register::Direction;
+
+    fn setup(chip: &RefCell<Ls7366Emulator>, mdr1: Mdr1) -> Ls7366<EmulatorDevice<'_>> {
+        let mut driver = Ls7366::new(EmulatorDevice::new(chip)).unwrap();
+        driver.configure(MDR0, mdr1).unwrap();
+        driver
+    }
+
+    /// Sends `pulses` clock pulses, with direction low for negative values.
+    fn pulse(chip: &RefCell<Ls7366Emulator>, pulses: i32) {
+        let mut chip = chip.borrow_mut();
+        let up = pulses > 0;
+        for _ in 0..pulses.abs() {
+            chip.apply(Inputs { a: false, b: up, index: false });
+            chip.apply(Inputs { a: true, b: up, index: false });
+        }
+        chip.apply(Inputs { a: false, b: up, index: false });
+    }
+
+    #[test]
+    fn test_counts_up_and_down() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut driver = setup(&chip, MDR1);
+
+        pulse(&chip, 25);
+        assert_eq!(driver.get_count().unwrap(), 25);
+        assert_eq!(driver.get_status().unwrap().count_direction, Direction::Up);
+
+        pulse(&chip, -40);
+        assert_eq!(driver.get_count().unwrap(), -15);
+        assert_eq!(driver.get_status().unwrap().count_direction, Direction::Down);
+    }
+
+    #[test]
+    fn test_direction_changes_without_clock_do_not_count() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut driver = setup(&chip, MDR1);
+        let mut generator = QuadratureGenerator::new(Signalling::ClockDirection).with_glitches(3);
+
+        for inputs in generator.run(ConstantVelocity::new(4, 10)) {
+            chip.borrow_mut().apply(inputs);
+        }
+        assert_eq!(driver.get_count().unwrap(), 40);
+        for inputs in generator.run(ConstantVelocity::new(-6, 10)) {
+            chip.borrow_mut().apply(inputs);
+        }
+        assert_eq!(driver.get_count().unwrap(), -20);
+        assert_eq!(driver.get_count().unwrap(), generator.expected_count(MDR0.quad_count_mode).unwrap());
+    }
+
+    #[test]
+    fn test_pulse_rate_across_wraps() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut driver = setup(&chip, Mdr1 { counter_mode: CounterMode::Byte1, ..MDR1 });
+        let mut generator = QuadratureGenerator::new(Signalling::ClockDirection);
+        let mut rate = PulseRate::new(CounterMode::Byte1, 5_000);
+
+        // 50 pulses per millisecond wrap the 1 byte counter every few samples.
+        let mut timestamp = 0;
+        for velocity in [50, 50, -20].iter() {
+            for _ in 0..10 {
+                rate.update(timestamp, driver.get_count().unwrap());
+                for inputs in generator.run(ConstantVelocity::new(*velocity, 1)) {
+                    chip.borrow_mut().apply(inputs);
+                }
+                timestamp += 1000;
+            }
+            assert_eq!(rate.update(timestamp, driver.get_count().unwrap()), Some(*velocity as f32 * 1000.0));
+        }
+
+        rate.reset();
+        assert_eq!(rate.rate(), None);
+        assert_eq!(rate.update(timestamp, 0), None);
+    }
+
+    #[test]
+    fn test_pulse_rate_ignores_old_samples() {
+        let mut rate = PulseRate::new(CounterMode::Byte4, 1_000);
+        rate.update(10_000, 0);
+        assert_eq!(rate.update(9_000, 500), None);
+        assert_eq!(rate.update(10_500, 10), None);
+        assert_eq!(rate.update(12_000, 40), Some(20_000.0));
+    }
+
+    #[test]
+    fn test_pulse_rate_rejects_non_increasing_timestamps() {
+        let mut rate = PulseRate::new(CounterMode::Byte4, 2_000);
+        rate.update(0, 0);
+        rate.update(1_000, 10);
+        // Repeated and out-of-order samples within the window are not counted.
+        assert_eq!(rate.update(1_000, 500), None);
+        assert_eq!(rate.update(500, -500), None);
+        assert_eq!(rate.update(2_000, 20), Some(10_000.0));
+        assert_eq!(rate.update(2_000, 900), Some(10_000.0));
+    }
+}
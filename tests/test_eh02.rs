@@ -0,0 +1,157 @@
+#![cfg(feature = "embedded-hal-02")]
+
+#[cfg(test)]
+mod tests {
+    use core::cell::{Cell, RefCell};
+
+    use embedded_hal::spi::{ErrorKind, ErrorType, Operation, SpiDevice};
+    use embedded_hal_02::{Direction, Qei};
+    use embedded_hal_mock::eh0::spi::{Mock, Transaction as SpiTransaction};
+
+    use ls7366::{Action, Encodable, Error, Ls7366, Target};
+    use ls7366::eh02::{Eh02Error, Eh02Qei, Eh02Spi, MAX_TRANSACTION};
+    use ls7366::emulator::{EmulatorDevice, Ls7366Emulator};
+    use ls7366::events::Events;
+    use ls7366::generator::{ConstantVelocity, QuadratureGenerator, Signalling};
+    use ls7366::ir::InstructionRegister;
+    use ls7366::mdr1::{CounterMode, Mdr1};
+
+    fn ir(target: Target, action: Action) -> u8 {
+        InstructionRegister { target, action }.encode()
+    }
+
+    #[test]
+    fn test_new_and_get_count() {
+        let expectations = [
+            SpiTransaction::write(vec![ir(Target::Mdr0, Action::Write), 0b00000011]),
+            SpiTransaction::write(vec![ir(Target::Mdr1, Action::Write), 0x00]),
+            SpiTransaction::write(vec![ir(Target::Dtr, Action::Write), 0x00, 0x00, 0x00, 0x00]),
+            SpiTransaction::write(vec![ir(Target::Cntr, Action::Load)]),
+            SpiTransaction::write(vec![ir(Target::Str, Action::Clear)]),
+            // The instruction and the data are clocked in one 0.2 transfer, keeping the chip selected.
+            SpiTransaction::transfer(
+                vec![ir(Target::Cntr, Action::Read), 0x00, 0x00, 0x00, 0x00],
+                vec![0x00, 0xFF, 0xFF, 0xFF, 0xFB],
+            ),
+        ];
+        let mut spi = Mock::new(&expectations);
+        let mut driver = Ls7366::new(Eh02Spi::new(spi.clone())).unwrap();
+
+        assert_eq!(driver.get_count().unwrap(), -5);
+        spi.done();
+    }
+
+    #[test]
+    fn test_transaction_is_one_transfer() {
+        let expectations = [
+            SpiTransaction::transfer(vec![0x01, 0x02, 0x03, 0x00, 0x00, 0x06], vec![0x10, 0x20, 0x30, 0x40, 0x50, 0x60]),
+        ];
+        let mut spi = Mock::new(&expectations);
+        let mut device = Eh02Spi::new(spi.clone());
+
+        let (mut read, mut transfer, mut in_place) = ([0x00; 2], [0x00; 1], [0x06]);
+        device.transaction(&mut [
+            Operation::Write(&[0x01]),
+            Operation::Transfer(&mut transfer, &[0x02, 0x03]),
+            Operation::Read(&mut read),
+            Operation::TransferInPlace(&mut in_place),
+        ]).unwrap();
+        assert_eq!(transfer, [0x20]);
+        assert_eq!(read, [0x40, 0x50]);
+        assert_eq!(in_place, [0x60]);
+        spi.done();
+    }
+
+    #[test]
+    fn test_transaction_limits() {
+        let mut spi = Mock::new(&[]);
+        let mut device = Eh02Spi::new(spi.clone());
+
+        let result = device.transaction(&mut [Operation::Write(&[0x00]), Operation::DelayNs(100)]);
+        assert!(matches!(result, Err(Eh02Error::DelayUnsupported)));
+        let result = device.transaction(&mut [Operation::Write(&[0x00; MAX_TRANSACTION + 1])]);
+        assert!(matches!(result, Err(Eh02Error::TransactionTooLong)));
+        spi.done();
+    }
+
+    /// Emulated chip whose bus can be made to fail.
+    struct FlakyDevice<'a> {
+        device: EmulatorDevice<'a>,
+        fail: &'a Cell<bool>,
+    }
+
+    impl ErrorType for FlakyDevice<'_> {
+        type Error = ErrorKind;
+    }
+
+    impl SpiDevice<u8> for FlakyDevice<'_> {
+        fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), ErrorKind> {
+            if self.fail.get() {
+                return Err(ErrorKind::Other);
+            }
+            self.device.transaction(operations).map_err(|never| match never {})
+        }
+    }
+
+    /// Moves the emulated encoder by `steps` steps, negative values turning backwards.
+    fn turn(chip: &RefCell<Ls7366Emulator>, encoder: &mut QuadratureGenerator, steps: i64) {
+        for inputs in encoder.run(ConstantVelocity::new(steps, 1)) {
+            chip.borrow_mut().apply(inputs);
+        }
+    }
+
+    /// Reads the position through the trait, like a consumer generic over `Qei`.
+    fn position<Q: Qei<Count = i64>>(qei: &Q) -> (i64, Direction) {
+        (qei.count(), qei.direction())
+    }
+
+    #[test]
+    fn test_qei_count_and_direction() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut encoder = QuadratureGenerator::new(Signalling::Quadrature);
+        let mut qei = Eh02Qei::new(Ls7366::new(EmulatorDevice::new(&chip)).unwrap());
+
+        turn(&chip, &mut encoder, 30);
+        assert_eq!(position(&qei), (30, Direction::Upcounting));
+        turn(&chip, &mut encoder, -45);
+        assert_eq!(position(&qei), (-15, Direction::Downcounting));
+
+        // Counts are sign-extended from the configured width.
+        qei.driver().set_counter_mode(CounterMode::Byte1).unwrap();
+        qei.driver().set_count(0).unwrap();
+        turn(&chip, &mut encoder, 200);
+        assert_eq!(position(&qei), (-56, Direction::Upcounting));
+        turn(&chip, &mut encoder, 100);
+        assert_eq!(position(&qei), (44, Direction::Upcounting));
+        // The carry latch read alongside the direction is kept for the driver.
+        assert!(qei.driver().poll_events().unwrap().contains(Events::CARRY));
+        assert!(qei.take_error().is_none());
+    }
+
+    #[test]
+    fn test_qei_repeats_last_values_on_error() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let fail = Cell::new(false);
+        let mut driver = Ls7366::new_uninit(FlakyDevice { device: EmulatorDevice::new(&chip), fail: &fail });
+        driver.configure(Default::default(), Mdr1::default()).unwrap();
+        let qei = Eh02Qei::new(driver);
+        // The power-on configuration counts clock and direction signals.
+        let mut encoder = QuadratureGenerator::new(Signalling::ClockDirection);
+
+        turn(&chip, &mut encoder, -3);
+        assert_eq!(position(&qei), (-3, Direction::Downcounting));
+
+        fail.set(true);
+        turn(&chip, &mut encoder, 1);
+        assert_eq!(position(&qei), (-3, Direction::Downcounting));
+        match qei.take_error() {
+            Some(Error::SpiError(ErrorKind::Other)) => {}
+            other => panic!("unexpected error {:?}", other),
+        }
+        assert!(qei.take_error().is_none());
+
+        fail.set(false);
+        assert_eq!(position(&qei), (-2, Direction::Upcounting));
+        assert_eq!(qei.release().get_count().unwrap(), -2);
+    }
+}
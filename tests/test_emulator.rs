@@ -0,0 +1,678 @@
+#[cfg(test)]
+mod tests {
+    use core::cell::{Cell, RefCell};
+
+    use embedded_hal::digital::{ErrorKind, ErrorType, InputPin};
+    use embedded_hal::spi::{self, Operation, SpiDevice};
+
+    use ls7366::compare::CompareSequence;
+    use ls7366::emulator::{EmulatorDevice, EmulatorFlagPin, Inputs, Ls7366Emulator};
+    use ls7366::homing::{HomeAction, HomeReport, Homing};
+    use ls7366::events::Events;
+    use ls7366::flags::FlagError;
+    use ls7366::generator::{ConstantVelocity, QuadratureGenerator, Signalling};
+    use ls7366::{Action, Encodable, Error, Ls7366, Supervision, Target};
+    use ls7366::ir::InstructionRegister;
+    use ls7366::mdr0::{CycleCountMode, IndexMode, Mdr0, QuadCountMode};
+    use ls7366::mdr1::{CounterMode, Mdr1};
+    use ls7366::multi_axis::SynchronizedAxes;
+    use ls7366::str_register::{Direction, SignBit};
+
+    fn setup(chip: &RefCell<Ls7366Emulator>, mdr0: Mdr0, mdr1: Mdr1) -> Ls7366<EmulatorDevice<'_>> {
+        let mut driver = Ls7366::new(EmulatorDevice::new(chip)).unwrap();
+        driver.configure(mdr0, mdr1).unwrap();
+        driver
+    }
+
+    fn x4() -> Mdr0 {
+        Mdr0 { quad_count_mode: QuadCountMode::Quad4x, ..Mdr0::default() }
+    }
+
+    fn byte1() -> Mdr1 {
+        Mdr1 { counter_mode: CounterMode::Byte1, ..Mdr1::default() }
+    }
+
+    /// Moves the encoder by `steps` quadrature states, negative values turning backwards.
+    fn turn(chip: &RefCell<Ls7366Emulator>, encoder: &mut QuadratureGenerator, steps: i64) {
+        for inputs in encoder.run(ConstantVelocity::new(steps, 1)) {
+            chip.borrow_mut().apply(inputs);
+        }
+    }
+
+    fn pulse_index(chip: &RefCell<Ls7366Emulator>) {
+        let inputs = chip.borrow().inputs();
+        chip.borrow_mut().apply(Inputs { index: !inputs.index, ..inputs });
+        chip.borrow_mut().apply(inputs);
+    }
+
+    #[test]
+    fn test_counts_per_quadrature_cycle() {
+        for &(mode, per_cycle) in [(QuadCountMode::Quad1x, 1), (QuadCountMode::Quad2x, 2), (QuadCountMode::Quad4x, 4)].iter() {
+            let chip = RefCell::new(Ls7366Emulator::new());
+            let mut encoder = QuadratureGenerator::new(Signalling::Quadrature);
+            let mut driver = setup(&chip, Mdr0 { quad_count_mode: mode, ..x4() }, Mdr1::default());
+
+            turn(&chip, &mut encoder, 4 * 10);
+            assert_eq!(driver.get_count().unwrap(), 10 * per_cycle);
+            turn(&chip, &mut encoder, -4 * 25);
+            assert_eq!(driver.get_count().unwrap(), -15 * per_cycle);
+            // Partial cycles back and forth never accumulate.
+            for _ in 0..5 {
+                turn(&chip, &mut encoder, 3);
+                turn(&chip, &mut encoder, -3);
+            }
+            assert_eq!(driver.get_count().unwrap(), -15 * per_cycle);
+        }
+    }
+
+    #[test]
+    fn test_invalid_transition_is_ignored() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut driver = setup(&chip, x4(), Mdr1::default());
+
+        chip.borrow_mut().apply(Inputs { a: true, b: true, index: false });
+        assert_eq!(driver.get_count().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_free_running_wraps_and_tracks_sign() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut encoder = QuadratureGenerator::new(Signalling::Quadrature);
+        let mut driver = setup(&chip, x4(), byte1());
+        driver.clear_status().unwrap();
+
+        turn(&chip, &mut encoder, -1);
+        assert_eq!(driver.get_count().unwrap(), -1);
+        let status = driver.get_status().unwrap();
+        assert!(status.borrow);
+        assert_eq!(status.sign_bit, SignBit::Negative);
+        assert_eq!(status.count_direction, Direction::Down);
+
+        turn(&chip, &mut encoder, 1);
+        let status = driver.get_status().unwrap();
+        assert!(status.cary);
+        assert_eq!(status.sign_bit, SignBit::Positive);
+        assert_eq!(status.count_direction, Direction::Up);
+        assert_eq!(driver.get_count().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_range_limit_freezes_at_bounds() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut encoder = QuadratureGenerator::new(Signalling::Quadrature);
+        let mut driver = setup(&chip, Mdr0 { cycle_count_mode: CycleCountMode::RangeLimit, ..x4() }, byte1());
+        driver.write_dtr(10).unwrap();
+        driver.clear_status().unwrap();
+
+        turn(&chip, &mut encoder, 15);
+        assert_eq!(driver.get_count().unwrap(), 10);
+        assert!(driver.get_status().unwrap().cary);
+
+        turn(&chip, &mut encoder, -20);
+        assert_eq!(driver.get_count().unwrap(), 0);
+        assert!(driver.get_status().unwrap().borrow);
+    }
+
+    #[test]
+    fn test_modulo_n_wraps_at_dtr() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut encoder = QuadratureGenerator::new(Signalling::Quadrature);
+        let mut driver = setup(&chip, Mdr0 { cycle_count_mode: CycleCountMode::ModuloN, ..x4() }, byte1());
+        driver.write_dtr(9).unwrap();
+
+        turn(&chip, &mut encoder, 23);
+        assert_eq!(driver.get_count().unwrap(), 3);
+        turn(&chip, &mut encoder, -5);
+        assert_eq!(driver.get_count().unwrap(), 8);
+    }
+
+    #[test]
+    fn test_configure_modulo_counts_unsigned() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut encoder = QuadratureGenerator::new(Signalling::Quadrature);
+        let mut driver = setup(&chip, x4(), byte1());
+        driver.configure_modulo(200).unwrap();
+        assert_eq!(driver.mdr0().cycle_count_mode, CycleCountMode::ModuloN);
+        assert_eq!(chip.borrow().dtr(), 199);
+
+        // Beyond the signed range of a single byte, without sign extension.
+        driver.set_count(150).unwrap();
+        assert_eq!(chip.borrow().dtr(), 199);
+        turn(&chip, &mut encoder, 60);
+        assert_eq!(driver.get_count().unwrap(), 10);
+        turn(&chip, &mut encoder, -11);
+        assert_eq!(driver.get_count().unwrap(), 199);
+        driver.latch_count().unwrap();
+        assert_eq!(driver.read_latched_count().unwrap(), 199);
+
+        assert!(driver.set_count(-1).is_err());
+        // Within the counter width but not below n.
+        assert!(matches!(driver.set_count(200), Err(Error::ValueOutOfRange)));
+        assert_eq!(driver.get_count().unwrap(), 199);
+        assert!(driver.set_count(256).is_err());
+        assert!(driver.configure_modulo(0).is_err());
+        assert!(driver.configure_modulo(257).is_err());
+        assert_eq!(chip.borrow().dtr(), 199);
+    }
+
+    #[test]
+    fn test_configure_range_limit_counts_unsigned() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut encoder = QuadratureGenerator::new(Signalling::Quadrature);
+        let mut driver = setup(&chip, x4(), byte1());
+        driver.configure_range_limit(250).unwrap();
+        assert_eq!(driver.mdr0().cycle_count_mode, CycleCountMode::RangeLimit);
+
+        turn(&chip, &mut encoder, 300);
+        assert_eq!(driver.get_count().unwrap(), 250);
+        turn(&chip, &mut encoder, -50);
+        assert_eq!(driver.get_count().unwrap(), 200);
+        turn(&chip, &mut encoder, -250);
+        assert_eq!(driver.get_count().unwrap(), 0);
+
+        assert!(matches!(driver.set_count(251), Err(Error::ValueOutOfRange)));
+        driver.set_count(250).unwrap();
+        assert_eq!(driver.get_count().unwrap(), 250);
+        assert_eq!(chip.borrow().dtr(), 250);
+        driver.set_count(0).unwrap();
+
+        // Back to signed counting once the cycle count mode is free running again.
+        driver.set_cycle_count_mode(CycleCountMode::FreeRunning).unwrap();
+        turn(&chip, &mut encoder, 200);
+        assert_eq!(driver.get_count().unwrap(), -56);
+    }
+
+    #[test]
+    fn test_single_cycle_stops_until_reloaded() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut encoder = QuadratureGenerator::new(Signalling::Quadrature);
+        let mut driver = setup(&chip, Mdr0 { cycle_count_mode: CycleCountMode::SingleCycle, ..x4() }, byte1());
+        driver.set_count(-3).unwrap();
+
+        turn(&chip, &mut encoder, 5);
+        assert_eq!(driver.get_count().unwrap(), 0);
+        assert!(!driver.get_status().unwrap().count_enabled);
+
+        driver.set_count(5).unwrap();
+        assert!(driver.get_status().unwrap().count_enabled);
+        turn(&chip, &mut encoder, 2);
+        assert_eq!(driver.get_count().unwrap(), 7);
+    }
+
+    #[test]
+    fn test_single_cycle_done() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut encoder = QuadratureGenerator::new(Signalling::Quadrature);
+        let mut driver = setup(&chip, x4(), Mdr1::default());
+        driver.start_single_cycle(-10).unwrap();
+        assert_eq!(driver.mdr0().cycle_count_mode, CycleCountMode::SingleCycle);
+
+        turn(&chip, &mut encoder, 9);
+        assert!(!driver.single_cycle_done().unwrap());
+        turn(&chip, &mut encoder, 1);
+        assert!(driver.single_cycle_done().unwrap());
+        turn(&chip, &mut encoder, 5);
+        assert_eq!(driver.get_count().unwrap(), 0);
+        // Stays done until started again.
+        assert!(driver.single_cycle_done().unwrap());
+
+        // Counting down from n - 1 stops on the nth count.
+        driver.start_single_cycle(4).unwrap();
+        assert!(!driver.single_cycle_done().unwrap());
+        turn(&chip, &mut encoder, -5);
+        assert!(driver.single_cycle_done().unwrap());
+        assert_eq!(driver.get_count().unwrap(), -1);
+        assert!(driver.poll_events().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_single_cycle_reload() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut encoder = QuadratureGenerator::new(Signalling::Quadrature);
+        let mut driver = setup(&chip, x4(), Mdr1::default());
+        driver.set_single_cycle_reload(true);
+        driver.start_single_cycle(-3).unwrap();
+
+        let mut cycles = 0;
+        for _ in 0..10 {
+            turn(&chip, &mut encoder, 1);
+            if driver.single_cycle_done().unwrap() {
+                cycles += 1;
+                assert_eq!(driver.get_count().unwrap(), -3);
+            }
+        }
+        assert_eq!(cycles, 3);
+        assert_eq!(driver.get_count().unwrap(), -2);
+    }
+
+    #[test]
+    fn test_single_cycle_not_done_while_disabled() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut encoder = QuadratureGenerator::new(Signalling::Quadrature);
+        let mut driver = setup(&chip, x4(), Mdr1::default());
+        driver.set_single_cycle_reload(true);
+        driver.start_single_cycle(-3).unwrap();
+
+        turn(&chip, &mut encoder, 1);
+        driver.set_disable_counting(true).unwrap();
+        turn(&chip, &mut encoder, 5);
+        assert!(!driver.single_cycle_done().unwrap());
+        // Not reloaded either.
+        assert_eq!(driver.get_count().unwrap(), -2);
+
+        driver.set_disable_counting(false).unwrap();
+        turn(&chip, &mut encoder, 2);
+        assert!(driver.single_cycle_done().unwrap());
+        assert_eq!(driver.get_count().unwrap(), -3);
+    }
+
+    #[test]
+    fn test_disable_counting() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut encoder = QuadratureGenerator::new(Signalling::Quadrature);
+        let mut driver = setup(&chip, x4(), Mdr1 { disable_counting: true, ..Mdr1::default() });
+
+        turn(&chip, &mut encoder, 8);
+        assert_eq!(driver.get_count().unwrap(), 0);
+        assert!(!driver.get_status().unwrap().count_enabled);
+    }
+
+    #[test]
+    fn test_compare_latch() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut encoder = QuadratureGenerator::new(Signalling::Quadrature);
+        let mut driver = setup(&chip, x4(), Mdr1::default());
+        driver.write_dtr(6).unwrap();
+        driver.clear_status().unwrap();
+
+        turn(&chip, &mut encoder, 5);
+        assert!(!driver.get_status().unwrap().compare);
+        turn(&chip, &mut encoder, 1);
+        assert!(driver.get_status().unwrap().compare);
+    }
+
+    #[test]
+    fn test_index_modes() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut encoder = QuadratureGenerator::new(Signalling::Quadrature);
+        let mut driver = setup(&chip, Mdr0 { index_mode: IndexMode::LoadCntr, ..x4() }, Mdr1::default());
+        driver.write_dtr(1000).unwrap();
+        turn(&chip, &mut encoder, 7);
+        pulse_index(&chip);
+        assert_eq!(driver.get_count().unwrap(), 1000);
+        assert!(driver.get_status().unwrap().index);
+
+        driver.set_index_mode(IndexMode::ClearCntr).unwrap();
+        turn(&chip, &mut encoder, 7);
+        pulse_index(&chip);
+        assert_eq!(driver.get_count().unwrap(), 0);
+
+        driver.set_index_mode(IndexMode::LoadOtr).unwrap();
+        turn(&chip, &mut encoder, 7);
+        pulse_index(&chip);
+        turn(&chip, &mut encoder, 3);
+        assert_eq!(driver.read_latched_count().unwrap(), 7);
+        assert_eq!(chip.borrow().cntr(), 10);
+
+        driver.set_index_mode(IndexMode::DisableIndex).unwrap();
+        driver.clear_status().unwrap();
+        pulse_index(&chip);
+        assert_eq!(driver.get_count().unwrap(), 10);
+        assert!(!driver.get_status().unwrap().index);
+    }
+
+    #[test]
+    fn test_inverted_index_triggers_on_falling_edge() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut encoder = QuadratureGenerator::new(Signalling::Quadrature);
+        let mut driver = setup(&chip, Mdr0 { index_mode: IndexMode::ClearCntr, is_index_inverted: true, ..x4() }, Mdr1::default());
+        turn(&chip, &mut encoder, 5);
+        let inputs = chip.borrow().inputs();
+
+        chip.borrow_mut().apply(Inputs { index: true, ..inputs });
+        assert_eq!(driver.get_count().unwrap(), 5);
+        chip.borrow_mut().apply(Inputs { index: false, ..inputs });
+        assert_eq!(driver.get_count().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_reading_status_clears_latches() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut driver = Ls7366::new_uninit(EmulatorDevice::new(&chip));
+
+        let status = driver.get_status().unwrap();
+        assert!(status.power_loss);
+        assert!(status.count_enabled);
+        let status = driver.get_status().unwrap();
+        assert!(!status.power_loss);
+        assert!(status.count_enabled);
+    }
+
+    #[test]
+    fn test_power_cycle_resets_registers() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut driver = setup(&chip, Mdr0 { quad_count_mode: QuadCountMode::Quad1x, ..x4() }, byte1());
+        driver.set_count(42).unwrap();
+
+        chip.borrow_mut().power_cycle();
+        assert_eq!(driver.read_mdr0().unwrap(), Mdr0::default());
+        assert_eq!(driver.read_mdr1().unwrap(), Mdr1::default());
+        assert!(driver.get_status().unwrap().power_loss);
+        assert_eq!(driver.get_count().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_read_count_loads_otr() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut encoder = QuadratureGenerator::new(Signalling::Quadrature);
+        let mut driver = setup(&chip, x4(), Mdr1::default());
+        turn(&chip, &mut encoder, 12);
+
+        assert_eq!(driver.get_count().unwrap(), 12);
+        turn(&chip, &mut encoder, 4);
+        assert_eq!(chip.borrow().otr(), 12);
+        assert_eq!(driver.read_latched_count().unwrap(), 12);
+        driver.latch_count().unwrap();
+        assert_eq!(driver.read_latched_count().unwrap(), 16);
+    }
+
+    #[test]
+    fn test_configuration_round_trip() {
+        let mdr0 = Mdr0 {
+            quad_count_mode: QuadCountMode::Quad2x,
+            cycle_count_mode: CycleCountMode::ModuloN,
+            index_mode: IndexMode::LoadOtr,
+            ..Mdr0::default()
+        };
+        let mdr1 = Mdr1 { counter_mode: CounterMode::Byte2, flag_on_cmp: true, ..Mdr1::default() };
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut driver = setup(&chip, mdr0, mdr1);
+        driver.configure(mdr0, mdr1).unwrap();
+        driver.write_dtr(0x1234).unwrap();
+
+        driver.verify_configuration().unwrap();
+        assert_eq!(chip.borrow().dtr(), 0x1234);
+    }
+
+    #[test]
+    fn test_poll_events_reports_each_event_once() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut encoder = QuadratureGenerator::new(Signalling::Quadrature);
+        let mut driver = Ls7366::new_uninit(EmulatorDevice::new(&chip));
+        assert_eq!(driver.poll_events().unwrap(), Events::POWER_LOSS);
+
+        let mut driver = setup(&chip, Mdr0 { index_mode: IndexMode::LoadOtr, ..x4() }, byte1());
+        driver.write_dtr(3).unwrap();
+        assert!(driver.poll_events().unwrap().is_empty());
+
+        turn(&chip, &mut encoder, 3);
+        pulse_index(&chip);
+        assert_eq!(driver.poll_events().unwrap(), Events::COMPARE | Events::INDEX);
+        assert!(driver.poll_events().unwrap().is_empty());
+
+        turn(&chip, &mut encoder, -4);
+        assert_eq!(driver.poll_events().unwrap(), Events::BORROW);
+        turn(&chip, &mut encoder, 1);
+        assert_eq!(driver.poll_events().unwrap(), Events::CARRY);
+        assert!(driver.poll_events().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_power_loss_supervision() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut encoder = QuadratureGenerator::new(Signalling::Quadrature);
+        let mdr0 = Mdr0 { cycle_count_mode: CycleCountMode::ModuloN, ..x4() };
+        let mut driver = setup(&chip, mdr0, Mdr1 { counter_mode: CounterMode::Byte2, ..Mdr1::default() });
+        driver.write_dtr(999).unwrap();
+        turn(&chip, &mut encoder, 1003);
+        assert_eq!(driver.get_count().unwrap(), 3);
+
+        // On demand only by default.
+        assert!(!driver.check_power_loss().unwrap());
+        chip.borrow_mut().power_cycle();
+        assert_eq!(driver.get_count().unwrap(), 0);
+        assert!(driver.check_power_loss().unwrap());
+        assert_eq!(chip.borrow().mdr0(), mdr0);
+        assert_eq!(chip.borrow().dtr(), 999);
+        assert_eq!(driver.get_count().unwrap(), 0);
+
+        driver.set_supervision(Supervision { check_on_read: true, restore_count: true });
+        turn(&chip, &mut encoder, 1005);
+        assert_eq!(driver.get_count().unwrap(), 5);
+        chip.borrow_mut().power_cycle();
+        assert_eq!(driver.get_count().unwrap(), 5);
+        assert_eq!(driver.read_mdr1().unwrap().counter_mode, CounterMode::Byte2);
+        turn(&chip, &mut encoder, 996);
+        assert_eq!(driver.get_count().unwrap(), 1);
+
+        let events = driver.poll_events().unwrap();
+        assert!(events.contains(Events::POWER_LOSS | Events::POWER_LOSS_RECOVERED | Events::CARRY));
+        assert!(driver.poll_events().unwrap().is_empty());
+
+        chip.borrow_mut().power_cycle();
+        assert_eq!(driver.snapshot().unwrap().count, 1);
+        assert_eq!(driver.poll_events().unwrap(), Events::POWER_LOSS | Events::POWER_LOSS_RECOVERED);
+    }
+
+    #[test]
+    fn test_power_loss_seen_by_other_reads() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mdr1 = Mdr1 { counter_mode: CounterMode::Byte2, ..Mdr1::default() };
+        let mut driver = setup(&chip, x4(), mdr1);
+
+        // Reading Str for the compare latch also clears the power-loss latch.
+        chip.borrow_mut().power_cycle();
+        assert!(!driver.check_compare().unwrap());
+        assert_eq!(chip.borrow().mdr1().counter_mode, CounterMode::Byte4);
+        assert!(driver.check_power_loss().unwrap());
+        assert_eq!(chip.borrow().mdr1(), mdr1);
+        assert!(!driver.check_power_loss().unwrap());
+        assert_eq!(driver.poll_events().unwrap(), Events::POWER_LOSS | Events::POWER_LOSS_RECOVERED);
+    }
+
+    /// An emulator device that turns the encoder right after the next write to `Dtr`.
+    struct MoveAfterDtrWrite<'a> {
+        device: EmulatorDevice<'a>,
+        chip: &'a RefCell<Ls7366Emulator>,
+        encoder: QuadratureGenerator,
+        steps: &'a Cell<i64>,
+    }
+
+    impl spi::ErrorType for MoveAfterDtrWrite<'_> {
+        type Error = core::convert::Infallible;
+    }
+
+    impl SpiDevice<u8> for MoveAfterDtrWrite<'_> {
+        fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+            let dtr_write = InstructionRegister { target: Target::Dtr, action: Action::Write }.encode();
+            let writes_dtr = matches!(operations.first(), Some(Operation::Write(words)) if words.first() == Some(&dtr_write));
+            self.device.transaction(operations)?;
+            if writes_dtr {
+                turn(self.chip, &mut self.encoder, self.steps.take());
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_compare_reached_while_arming() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let steps = Cell::new(0);
+        let device = MoveAfterDtrWrite {
+            device: EmulatorDevice::new(&chip),
+            chip: &chip,
+            encoder: QuadratureGenerator::new(Signalling::Quadrature),
+            steps: &steps,
+        };
+        let mut driver = Ls7366::new_uninit(device);
+        driver.configure(x4(), Mdr1::default()).unwrap();
+
+        // The counter reaches the new position before arming has finished.
+        steps.set(5);
+        driver.arm_compare(5).unwrap();
+        assert_eq!(chip.borrow().cntr(), 5);
+        assert!(driver.check_compare().unwrap());
+    }
+
+    #[test]
+    fn test_compare_sequence() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut encoder = QuadratureGenerator::new(Signalling::Quadrature);
+        let mut driver = setup(&chip, x4(), Mdr1 { counter_mode: CounterMode::Byte2, ..Mdr1::default() });
+        let mut triggers = CompareSequence::new(&[-10, -40, -25]);
+        assert_eq!(triggers.start(&mut driver).unwrap(), Some(-10));
+
+        let mut reached = Vec::new();
+        for &steps in [-5, -5, -5, -30, 5, 5, 5, 5].iter() {
+            turn(&chip, &mut encoder, steps);
+            reached.extend(triggers.poll(&mut driver).unwrap());
+        }
+        assert_eq!(reached, [-10, -40, -25]);
+        assert!(triggers.is_done());
+        assert_eq!(triggers.poll(&mut driver).unwrap(), None);
+        assert_eq!(driver.poll_events().unwrap(), Events::BORROW);
+    }
+
+    #[test]
+    fn test_homing_clears_on_index() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut encoder = QuadratureGenerator::new(Signalling::Quadrature);
+        let mdr0 = Mdr0 { index_mode: IndexMode::LoadOtr, ..x4() };
+        let mut driver = setup(&chip, mdr0, Mdr1 { flag_on_cy: true, ..Mdr1::default() });
+        driver.set_count(-300).unwrap();
+        driver.write_dtr(77).unwrap();
+        // An index seen before homing must not end the search.
+        pulse_index(&chip);
+
+        let mut calls = 0;
+        let report = Homing::new(HomeAction::ClearCount).run(&mut driver, |_| {
+            calls += 1;
+            turn(&chip, &mut encoder, -3);
+            if calls == 10 {
+                pulse_index(&chip);
+                turn(&chip, &mut encoder, -2);
+            }
+            true
+        }).unwrap().unwrap();
+
+        assert_eq!(report, HomeReport { pre_home_position: Some(-327), position: -2 });
+        assert_eq!(calls, 10);
+        // Run-mode configuration is back.
+        assert_eq!(driver.read_mdr0().unwrap(), mdr0);
+        assert!(driver.read_mdr1().unwrap().flag_on_cy);
+        assert_eq!(chip.borrow().dtr(), 77);
+    }
+
+    #[test]
+    fn test_homing_gives_up() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut encoder = QuadratureGenerator::new(Signalling::Quadrature);
+        let mut driver = setup(&chip, x4(), Mdr1::default());
+
+        let report = Homing::new(HomeAction::LoadCount(1000)).run(&mut driver, |count| {
+            turn(&chip, &mut encoder, 5);
+            count < 20
+        }).unwrap();
+        assert_eq!(report, None);
+        assert_eq!(driver.read_mdr0().unwrap(), x4());
+        assert_eq!(chip.borrow().dtr(), 0);
+        assert_eq!(driver.get_count().unwrap(), 25);
+    }
+
+    #[test]
+    fn test_homing_with_flag_pin() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut encoder = QuadratureGenerator::new(Signalling::Quadrature);
+        let mut driver = setup(&chip, x4(), Mdr1::default());
+        let mut flag = EmulatorFlagPin::new(&chip);
+
+        let mut calls = 0;
+        let report = Homing::new(HomeAction::LoadCount(1000)).run_with_flag(&mut driver, &mut flag, |_| {
+            calls += 1;
+            turn(&chip, &mut encoder, 4);
+            if calls == 3 {
+                pulse_index(&chip);
+            }
+            true
+        }).unwrap().unwrap();
+
+        assert_eq!(report, HomeReport { pre_home_position: Some(8), position: 1000 });
+        assert!(!driver.read_mdr1().unwrap().flag_on_idx);
+        assert!(driver.poll_events().unwrap().is_empty());
+    }
+
+    struct BrokenPin;
+
+    impl ErrorType for BrokenPin {
+        type Error = ErrorKind;
+    }
+
+    impl InputPin for BrokenPin {
+        fn is_high(&mut self) -> Result<bool, ErrorKind> {
+            Err(ErrorKind::Other)
+        }
+
+        fn is_low(&mut self) -> Result<bool, ErrorKind> {
+            Err(ErrorKind::Other)
+        }
+    }
+
+    #[test]
+    fn test_homing_restores_on_error() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut driver = setup(&chip, x4(), Mdr1::default());
+
+        let result = Homing::new(HomeAction::LoadCount(1000)).run_with_flag(&mut driver, &mut BrokenPin, |_| true);
+        assert!(matches!(result, Err(FlagError::Pin(ErrorKind::Other))));
+        assert_eq!(driver.read_mdr0().unwrap(), x4());
+        assert!(!driver.read_mdr1().unwrap().flag_on_idx);
+        assert_eq!(chip.borrow().dtr(), 0);
+    }
+
+    /// An emulator device whose transactions fail while `fail` is set.
+    struct FlakyDevice<'a> {
+        device: EmulatorDevice<'a>,
+        fail: &'a Cell<bool>,
+    }
+
+    impl spi::ErrorType for FlakyDevice<'_> {
+        type Error = spi::ErrorKind;
+    }
+
+    impl SpiDevice<u8> for FlakyDevice<'_> {
+        fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), spi::ErrorKind> {
+            if self.fail.get() {
+                return Err(spi::ErrorKind::Other);
+            }
+            self.device.transaction(operations).map_err(|never| match never {})
+        }
+    }
+
+    #[test]
+    fn test_synchronized_axes_hand_back_drivers_on_error() {
+        let chips = [RefCell::new(Ls7366Emulator::new()), RefCell::new(Ls7366Emulator::new())];
+        let fails = [Cell::new(false), Cell::new(true)];
+        let drivers = [0, 1].map(|axis| Ls7366::new_uninit(FlakyDevice { device: EmulatorDevice::new(&chips[axis]), fail: &fails[axis] }));
+
+        // The first axis is armed, then put back when the second one fails.
+        let drivers = match SynchronizedAxes::new(drivers) {
+            Err((Error::SpiError(spi::ErrorKind::Other), drivers)) => drivers,
+            _ => panic!("arming should fail"),
+        };
+        assert_eq!(chips[0].borrow().mdr0().index_mode, IndexMode::DisableIndex);
+        assert_eq!(drivers[0].mdr0().index_mode, IndexMode::DisableIndex);
+
+        fails[1].set(false);
+        let axes = SynchronizedAxes::new(drivers).map_err(|(error, _)| error).unwrap();
+        assert_eq!(chips[1].borrow().mdr0().index_mode, IndexMode::LoadOtr);
+
+        // A failing axis does not keep the others from being restored.
+        fails[0].set(true);
+        let drivers = match axes.release() {
+            Err((Error::SpiError(spi::ErrorKind::Other), drivers)) => drivers,
+            _ => panic!("releasing should fail"),
+        };
+        assert_eq!(chips[1].borrow().mdr0().index_mode, IndexMode::DisableIndex);
+        assert_eq!(drivers[0].mdr0().index_mode, IndexMode::LoadOtr);
+    }
+}
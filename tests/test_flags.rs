@@ -0,0 +1,129 @@
+#[cfg(test)]
+mod tests {
+    use core::cell::RefCell;
+    use std::thread;
+
+    use embedded_hal_mock::eh1::digital::{Mock as PinMock, State as PinState, Transaction as PinTransaction};
+
+    use ls7366::emulator::{EmulatorDevice, EmulatorFlagPin, Inputs, Ls7366Emulator};
+    use ls7366::events::Events;
+    use ls7366::flags::{EventQueue, FlagError, FlagHandler};
+    use ls7366::Ls7366;
+    use ls7366::mdr0::{IndexMode, Mdr0, QuadCountMode};
+    use ls7366::mdr1::Mdr1;
+
+    fn driver(chip: &RefCell<Ls7366Emulator>) -> Ls7366<EmulatorDevice<'_>> {
+        let mut driver = Ls7366::new(EmulatorDevice::new(chip)).unwrap();
+        let mdr0 = Mdr0 { quad_count_mode: QuadCountMode::Quad4x, index_mode: IndexMode::LoadOtr, ..Mdr0::default() };
+        driver.configure(mdr0, Mdr1::default()).unwrap();
+        driver
+    }
+
+    fn pulse_index(chip: &RefCell<Ls7366Emulator>) {
+        chip.borrow_mut().apply(Inputs { index: true, ..Inputs::default() });
+        chip.borrow_mut().apply(Inputs::default());
+    }
+
+    #[test]
+    fn test_queue_order_and_capacity() {
+        let queue: EventQueue<3> = EventQueue::new();
+        assert!(queue.is_empty());
+        assert_eq!(queue.pop(), None);
+
+        for round in 0..5 {
+            queue.push(Events::CARRY).unwrap();
+            queue.push(Events::INDEX | Events::COMPARE).unwrap();
+            queue.push(Events::BORROW).unwrap();
+            assert_eq!(queue.push(Events::POWER_LOSS), Err(Events::POWER_LOSS), "round {}", round);
+            assert_eq!(queue.len(), 3);
+
+            assert_eq!(queue.pop(), Some(Events::CARRY));
+            assert_eq!(queue.pop(), Some(Events::INDEX | Events::COMPARE));
+            assert_eq!(queue.pop(), Some(Events::BORROW));
+            assert_eq!(queue.pop(), None);
+        }
+    }
+
+    #[test]
+    fn test_queue_across_threads() {
+        static QUEUE: EventQueue<4> = EventQueue::new();
+        const COUNT: u16 = 10_000;
+
+        let producer = thread::spawn(|| {
+            for i in 0..COUNT {
+                let events = Events::from_bits_truncate(i % 64);
+                while QUEUE.push(events).is_err() {
+                    thread::yield_now();
+                }
+            }
+        });
+        for i in 0..COUNT {
+            let events = loop {
+                match QUEUE.pop() {
+                    Some(events) => break events,
+                    None => thread::yield_now(),
+                }
+            };
+            assert_eq!(events.bits(), i % 64);
+        }
+        producer.join().unwrap();
+        assert!(QUEUE.is_empty());
+    }
+
+    #[test]
+    fn test_handler_queues_flagged_events() {
+        let queue: EventQueue<4> = EventQueue::new();
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut lflag = EmulatorFlagPin::new(&chip);
+        let mut handler = FlagHandler::new(driver(&chip), &queue, Events::INDEX | Events::COMPARE).unwrap();
+        let mdr1 = handler.driver().mdr1();
+        assert!(mdr1.flag_on_idx && mdr1.flag_on_cmp && !mdr1.flag_on_cy && !mdr1.flag_on_bw);
+
+        // Nothing flagged: the chip is not touched.
+        assert_eq!(handler.handle_pin(&mut lflag).unwrap(), Events::empty());
+        assert!(queue.is_empty());
+
+        pulse_index(&chip);
+        assert!(chip.borrow().lflag());
+        assert_eq!(handler.handle_pin(&mut lflag).unwrap(), Events::INDEX);
+        assert!(!chip.borrow().lflag());
+        assert_eq!(queue.pop(), Some(Events::INDEX));
+
+        // Spurious interrupt without a latched event.
+        assert_eq!(handler.handle().unwrap(), Events::empty());
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_handler_reports_full_queue() {
+        let queue: EventQueue<1> = EventQueue::new();
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut handler = FlagHandler::new(driver(&chip), &queue, Events::INDEX).unwrap();
+
+        pulse_index(&chip);
+        handler.handle().unwrap();
+        pulse_index(&chip);
+        match handler.handle() {
+            Err(FlagError::QueueFull(events)) => assert_eq!(events, Events::INDEX),
+            _ => panic!("expected a full queue"),
+        }
+        assert_eq!(queue.pop(), Some(Events::INDEX));
+        assert!(handler.release().set_flag_events(Events::empty()).is_ok());
+    }
+
+    #[test]
+    fn test_handler_reads_pin() {
+        let queue: EventQueue<2> = EventQueue::new();
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut handler = FlagHandler::new(driver(&chip), &queue, Events::INDEX).unwrap();
+        let mut pin = PinMock::new(&[
+            PinTransaction::get(PinState::High),
+            PinTransaction::get(PinState::Low),
+        ]);
+
+        pulse_index(&chip);
+        assert_eq!(handler.handle_pin(&mut pin).unwrap(), Events::empty());
+        assert_eq!(handler.handle_pin(&mut pin).unwrap(), Events::INDEX);
+        pin.done();
+    }
+}
@@ -0,0 +1,118 @@
+#[cfg(test)]
+mod tests {
+    use core::cell::RefCell;
+
+    use ls7366::emulator::{EmulatorDevice, Ls7366Emulator};
+    use ls7366::generator::{ConstantVelocity, Jitter, QuadratureGenerator, Signalling, Trapezoidal};
+    use ls7366::Ls7366;
+    use ls7366::mdr0::{IndexMode, Mdr0, QuadCountMode};
+    use ls7366::mdr1::Mdr1;
+
+    const QUAD_MODES: [QuadCountMode; 3] = [QuadCountMode::Quad1x, QuadCountMode::Quad2x, QuadCountMode::Quad4x];
+
+    /// Feeds `profile` to a fresh emulator in `mode`, checking the count after every tick.
+    fn check_profile<P: Iterator<Item = i64>>(mode: QuadCountMode, mut generator: QuadratureGenerator, profile: P) -> i64 {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut driver = Ls7366::new(EmulatorDevice::new(&chip)).unwrap();
+        driver.set_quad_mode(mode).unwrap();
+
+        for steps in profile {
+            for inputs in generator.run(core::iter::once(steps)) {
+                chip.borrow_mut().apply(inputs);
+            }
+            assert_eq!(driver.get_count().unwrap(), generator.expected_count(mode).unwrap());
+        }
+        generator.position()
+    }
+
+    #[test]
+    fn test_constant_velocity() {
+        for &mode in QUAD_MODES.iter() {
+            let position = check_profile(mode, QuadratureGenerator::new(Signalling::Quadrature), ConstantVelocity::new(7, 20));
+            assert_eq!(position, 140);
+        }
+        let position = check_profile(
+            QuadCountMode::NonQuad,
+            QuadratureGenerator::new(Signalling::ClockDirection),
+            ConstantVelocity::new(-3, 20),
+        );
+        assert_eq!(position, -60);
+    }
+
+    #[test]
+    fn test_trapezoidal_move() {
+        let profile = Trapezoidal::new(1000, 40, 3);
+        assert_eq!(profile.clone().sum::<i64>(), 1000);
+        assert_eq!(profile.clone().max(), Some(40));
+        // Starts and ends slowly.
+        assert_eq!(profile.clone().next(), Some(3));
+        assert!(profile.clone().last().unwrap() <= 3);
+
+        for &mode in QUAD_MODES.iter() {
+            let position = check_profile(mode, QuadratureGenerator::new(Signalling::Quadrature), Trapezoidal::new(-1001, 40, 3));
+            assert_eq!(position, -1001);
+        }
+    }
+
+    #[test]
+    fn test_direction_reversals() {
+        for &mode in QUAD_MODES.iter() {
+            let profile = ConstantVelocity::new(5, 9)
+                .chain(ConstantVelocity::new(-3, 21))
+                .chain(ConstantVelocity::new(1, 7))
+                .chain(ConstantVelocity::new(-1, 3));
+            let position = check_profile(mode, QuadratureGenerator::new(Signalling::Quadrature), profile);
+            assert_eq!(position, 45 - 63 + 7 - 3);
+        }
+        let profile = ConstantVelocity::new(4, 5).chain(ConstantVelocity::new(-2, 13));
+        let position = check_profile(QuadCountMode::NonQuad, QuadratureGenerator::new(Signalling::ClockDirection), profile);
+        assert_eq!(position, -6);
+    }
+
+    #[test]
+    fn test_jitter_does_not_accumulate() {
+        let profile = Jitter::new(ConstantVelocity::new(2, 50), 5, 0xdead_beef);
+        assert_eq!(profile.clone().sum::<i64>(), 100);
+        assert!(profile.clone().any(|steps| steps < 0));
+
+        for &mode in QUAD_MODES.iter() {
+            let profile = Jitter::new(Trapezoidal::new(500, 20, 2), 3, 42);
+            let position = check_profile(mode, QuadratureGenerator::new(Signalling::Quadrature), profile);
+            assert_eq!(position, 500);
+        }
+    }
+
+    #[test]
+    fn test_glitches_are_rejected() {
+        for &mode in QUAD_MODES.iter() {
+            let generator = QuadratureGenerator::new(Signalling::Quadrature).with_glitches(3);
+            let profile = ConstantVelocity::new(5, 10).chain(ConstantVelocity::new(-4, 10));
+            assert_eq!(check_profile(mode, generator, profile), 10);
+        }
+        let generator = QuadratureGenerator::new(Signalling::ClockDirection).with_glitches(2);
+        assert_eq!(check_profile(QuadCountMode::NonQuad, generator, ConstantVelocity::new(3, 10)), 30);
+    }
+
+    #[test]
+    fn test_mismatched_signalling_has_no_expected_count() {
+        let generator = QuadratureGenerator::new(Signalling::Quadrature);
+        assert_eq!(generator.expected_count(QuadCountMode::NonQuad), None);
+        let generator = QuadratureGenerator::new(Signalling::ClockDirection);
+        assert_eq!(generator.expected_count(QuadCountMode::Quad4x), None);
+    }
+
+    #[test]
+    fn test_index_once_per_revolution() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut driver = Ls7366::new(EmulatorDevice::new(&chip)).unwrap();
+        let mdr0 = Mdr0 { quad_count_mode: QuadCountMode::Quad4x, index_mode: IndexMode::LoadOtr, ..Mdr0::default() };
+        driver.configure(mdr0, Mdr1::default()).unwrap();
+
+        let mut generator = QuadratureGenerator::new(Signalling::Quadrature).with_index(400);
+        for inputs in generator.run(ConstantVelocity::new(10, 45)) {
+            chip.borrow_mut().apply(inputs);
+        }
+        assert_eq!(driver.read_latched_count().unwrap(), 400);
+        assert_eq!(driver.get_count().unwrap(), 450);
+    }
+}
@@ -0,0 +1,64 @@
+#[cfg(test)]
+mod tests {
+    use core::cell::RefCell;
+    use core::f64::consts::PI;
+
+    use ls7366::emulator::{EmulatorDevice, Ls7366Emulator};
+    use ls7366::geometry::EncoderGeometry;
+    use ls7366::Ls7366;
+    use ls7366::mdr0::QuadCountMode;
+
+    #[test]
+    fn test_quad_multiplier() {
+        let geometry = EncoderGeometry::new(1000);
+        assert_eq!(geometry.scale(QuadCountMode::NonQuad).counts_per_revolution(), 1000.0);
+        assert_eq!(geometry.scale(QuadCountMode::Quad1x).counts_per_revolution(), 1000.0);
+        assert_eq!(geometry.scale(QuadCountMode::Quad2x).counts_per_revolution(), 2000.0);
+        assert_eq!(geometry.scale(QuadCountMode::Quad4x).counts_per_revolution(), 4000.0);
+    }
+
+    #[test]
+    fn test_scale_follows_driver_configuration() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut driver = Ls7366::new(EmulatorDevice::new(&chip)).unwrap();
+        let geometry = EncoderGeometry::new(256);
+
+        assert_eq!(geometry.scale_for(&driver).revolutions(1024), 1.0);
+        driver.set_quad_mode(QuadCountMode::Quad2x).unwrap();
+        assert_eq!(geometry.scale_for(&driver).revolutions(1024), 2.0);
+    }
+
+    #[test]
+    fn test_angles() {
+        let scale = EncoderGeometry::new(90).scale(QuadCountMode::Quad4x);
+        assert_eq!(scale.degrees(1), 1.0);
+        assert_eq!(scale.degrees(-540), -540.0);
+        assert!((scale.radians(180) - PI).abs() < 1e-12);
+        assert_eq!(scale.counts_from_degrees(-45.0), -45);
+        assert_eq!(scale.counts_from_radians(PI / 2.0), 90);
+        assert_eq!(scale.counts_from_revolutions(2.5), 900);
+    }
+
+    #[test]
+    fn test_gearbox_and_lead_screw() {
+        let geometry = EncoderGeometry::new(100).with_gear_ratio(3.0);
+        let scale = geometry.scale(QuadCountMode::Quad4x);
+        assert_eq!(scale.counts_per_revolution(), 1200.0);
+        assert_eq!(scale.millimetres(1200), None);
+        assert_eq!(scale.counts_from_millimetres(1.0), None);
+
+        let scale = geometry.with_lead(2.0).scale(QuadCountMode::Quad4x);
+        assert_eq!(scale.millimetres(1800), Some(3.0));
+        assert_eq!(scale.millimetres(-600), Some(-1.0));
+        assert_eq!(scale.counts_from_millimetres(-3.0), Some(-1800));
+    }
+
+    #[test]
+    fn test_rounds_to_nearest_count() {
+        let scale = EncoderGeometry::new(1).scale(QuadCountMode::Quad4x);
+        assert_eq!(scale.counts_from_revolutions(0.6), 2);
+        assert_eq!(scale.counts_from_revolutions(0.65), 3);
+        assert_eq!(scale.counts_from_revolutions(-0.6), -2);
+        assert_eq!(scale.counts_from_revolutions(-0.65), -3);
+    }
+}
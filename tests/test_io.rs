@@ -1,62 +1,181 @@
 #[cfg(test)]
 mod tests {
-    use embedded_hal_mock::spi::{Mock, Transaction as SpiTransaction};
+    use core::cell::RefCell;
+    use core::convert::Infallible;
+
+    use embedded_hal::digital::{self, OutputPin};
+    use embedded_hal::spi::{self, Operation, SpiBus, SpiDevice};
+    use embedded_hal_mock::eh1::digital::{Mock as PinMock, State as PinState, Transaction as PinTransaction};
+    use embedded_hal_mock::eh1::spi::{Mock, Transaction};
 
     use ls7366::{Action, Encodable, Target};
+    use ls7366::bus::{BusError, SharedBus};
+    use ls7366::clock_direction;
+    use ls7366::events::Events;
     use ls7366::ir::InstructionRegister;
-    use ls7366::Ls7366;
+    use ls7366::{Error, Ls7366, Supervision};
+    use ls7366::mdr0::{CycleCountMode, FilterClockDivisionFactor, IndexMode, Mdr0, QuadCountMode};
+    use ls7366::mdr1::{CounterMode, Mdr1};
+    use ls7366::multi_axis::SynchronizedAxes;
     use ls7366::str_register;
 
+    type SpiTransaction = Transaction<u8>;
+
+    /// Expectations for a single chip-select framed transaction.
+    fn transaction(operations: Vec<SpiTransaction>) -> Vec<SpiTransaction> {
+        let mut expectations = vec![SpiTransaction::transaction_start()];
+        expectations.extend(operations);
+        expectations.push(SpiTransaction::transaction_end());
+        expectations
+    }
+
+    fn command(target: Target, action: Action) -> Vec<SpiTransaction> {
+        transaction(vec![SpiTransaction::write_vec(vec![InstructionRegister { target, action }.encode()])])
+    }
+
+    fn write(target: Target, data: &[u8]) -> Vec<SpiTransaction> {
+        let mut request = vec![InstructionRegister {
+            target,
+            action: Action::Write,
+        }.encode()];
+        request.extend_from_slice(data);
+        transaction(vec![SpiTransaction::write_vec(request)])
+    }
+
+    fn read(target: Target, response: &[u8]) -> Vec<SpiTransaction> {
+        transaction(vec![
+            SpiTransaction::write_vec(vec![InstructionRegister {
+                target,
+                action: Action::Read,
+            }.encode()]),
+            SpiTransaction::read_vec(response.to_vec()),
+        ])
+    }
+
+    fn write_mdr0(raw: u8) -> Vec<SpiTransaction> {
+        write(Target::Mdr0, &[raw])
+    }
+
+    fn write_mdr1(counter_mode: CounterMode) -> Vec<SpiTransaction> {
+        write(Target::Mdr1, &[Mdr1 {
+            counter_mode,
+            disable_counting: false,
+            flag_on_idx: false,
+            flag_on_cmp: false,
+            flag_on_bw: false,
+            flag_on_cy: false,
+        }.encode()])
+    }
+
+    fn write_dtr(data: &[u8]) -> Vec<SpiTransaction> {
+        write(Target::Dtr, data)
+    }
+
+    fn read_cntr(response: &[u8]) -> Vec<SpiTransaction> {
+        read(Target::Cntr, response)
+    }
+
+    fn read_otr(response: &[u8]) -> Vec<SpiTransaction> {
+        read(Target::Otr, response)
+    }
+
+    fn read_str(response: u8) -> Vec<SpiTransaction> {
+        read(Target::Str, &[0x00, 0x00, 0x00, response])
+    }
+
+    fn read_mdr(target: Target, response: u8) -> Vec<SpiTransaction> {
+        read(target, &[response])
+    }
+
+    fn check_counts(counter_mode: CounterMode, reads: &[(&[u8], i64)]) {
+        let mut expectations = write_mdr1(counter_mode);
+        for (response, _) in reads {
+            expectations.extend(read_cntr(response));
+        }
+
+        let mut spi = Mock::new(&expectations);
+        let mut driver = Ls7366::new_uninit(spi.clone());
+        driver.write_register(Target::Mdr1, &[Mdr1 {
+            counter_mode,
+            disable_counting: false,
+            flag_on_idx: false,
+            flag_on_cmp: false,
+            flag_on_bw: false,
+            flag_on_cy: false,
+        }.encode()]).unwrap();
+
+        for (_, expected) in reads {
+            assert_eq!(driver.get_count().unwrap(), *expected);
+        }
+        spi.done();
+    }
+
     #[test]
     fn test_get_count() {
+        // A fresh driver assumes the chip's power-on 4 byte counter.
         let expectations = [
-            SpiTransaction::transfer(vec![InstructionRegister {
-                target: Target::Cntr,
-                action: Action::Read,
-            }.encode(), 0x00, 0x00, 0x00, 0x00], vec![0x00, 0xDE, 0xAD, 0xBE, 0xEF]),
-            // STR read, will return positive sign
-            SpiTransaction::transfer(vec![InstructionRegister {
-                target: Target::Str,
-                action: Action::Read,
-            }.encode(), 0x00, 0x00, 0x00, 0x00], vec![0x00, 0x00, 0x00, 0x00, 0b00001010],
-            ),
-            SpiTransaction::transfer(vec![InstructionRegister {
-                target: Target::Cntr,
-                action: Action::Read,
-            }.encode(), 0x00, 0x00, 0x00, 0x00], vec![0x00, 0xDE, 0xAD, 0xBE, 0xEF]),
-            // STR read, will return negative sign
-            SpiTransaction::transfer(vec![InstructionRegister {
-                target: Target::Str,
-                action: Action::Read,
-            }.encode(), 0x00, 0x00, 0x00, 0x00], vec![0x00, 0x00, 0x00, 0x00, 0b00001011],
-            )
-        ];
+            read_cntr(&[0x12, 0x34, 0x56, 0x78]),
+            read_cntr(&[0xDE, 0xAD, 0xBE, 0xEF]),
+        ].concat();
 
-        let spi = Mock::new(&expectations);
-        let mut driver = Ls7366::new_uninit(spi);
+        let mut spi = Mock::new(&expectations);
+        let mut driver = Ls7366::new_uninit(spi.clone());
 
         let result = driver.get_count().unwrap();
 
-        assert_eq!(result, 0xDEADBEEF);
-        assert_eq!(driver.get_count().unwrap(), -0xDEADBEEF);
+        assert_eq!(result, 0x12345678);
+        assert_eq!(driver.get_count().unwrap(), 0xDEADBEEFu32 as i32 as i64);
+        spi.done();
+    }
+
+    #[test]
+    fn test_get_count_byte4() {
+        check_counts(CounterMode::Byte4, &[
+            (&[0x7F, 0xFF, 0xFF, 0xFF], 2147483647),
+            (&[0x00, 0x00, 0x00, 0x01], 1),
+            (&[0xFF, 0xFF, 0xFF, 0xFF], -1),
+            (&[0x80, 0x00, 0x00, 0x00], -2147483648),
+        ]);
+    }
+
+    #[test]
+    fn test_get_count_byte3() {
+        check_counts(CounterMode::Byte3, &[
+            (&[0x7F, 0xFF, 0xFF], 8388607),
+            (&[0x00, 0x01, 0x00], 256),
+            (&[0xFF, 0xFF, 0xFE], -2),
+            (&[0x80, 0x00, 0x00], -8388608),
+        ]);
+    }
+
+    #[test]
+    fn test_get_count_byte2() {
+        check_counts(CounterMode::Byte2, &[
+            (&[0x7F, 0xFF], 32767),
+            (&[0x00, 0x2A], 42),
+            (&[0xFF, 0xD6], -42),
+            (&[0x80, 0x00], -32768),
+        ]);
+    }
+
+    #[test]
+    fn test_get_count_byte1() {
+        check_counts(CounterMode::Byte1, &[
+            (&[0x7F], 127),
+            (&[0x00], 0),
+            (&[0xFF], -1),
+            (&[0x80], -128),
+        ]);
     }
 
     #[test]
     fn test_status_a() {
         let expectations = [
             // STR read, will return positive sign
-            SpiTransaction::transfer(vec![InstructionRegister {
-                target: Target::Str,
-                action: Action::Read,
-            }.encode(), 0x00, 0x00, 0x00, 0x00], vec![0x00, 0x00, 0x00, 0x00, 0b00001010],
-            ),
+            read_str(0b00001010),
             // STR read, will return negative sign
-            SpiTransaction::transfer(vec![InstructionRegister {
-                target: Target::Str,
-                action: Action::Read,
-            }.encode(), 0x00, 0x00, 0x00, 0x00], vec![0x00, 0x00, 0x00, 0x00, 0b11110101],
-            )
-        ];
+            read_str(0b11110101),
+        ].concat();
         let expected_results = [
             str_register::Str {
                 cary: false,
@@ -79,26 +198,22 @@ mod tests {
                 sign_bit: str_register::SignBit::Negative,
             }
         ];
-        let spi = Mock::new(&expectations);
-        let mut driver = Ls7366::new_uninit(spi);
+        let mut spi = Mock::new(&expectations);
+        let mut driver = Ls7366::new_uninit(spi.clone());
 
         for payload in expected_results.iter() {
             let result = driver.get_status().unwrap();
             assert_eq!(&result, payload);
         }
+        spi.done();
     }
 
     #[test]
     fn test_status_b() {
-        let expectations = [
-            // STR read, will return positive sign
-            SpiTransaction::transfer(vec![InstructionRegister {
-                target: Target::Str,
-                action: Action::Read,
-            }.encode(), 0x00, 0x00, 0x00, 0x00], vec![0x00, 0x00, 0x00, 0x00, 0b00000100],
-            )];
-        let spi = Mock::new(&expectations);
-        let mut driver = Ls7366::new_uninit(spi);
+        // STR read, will return positive sign
+        let expectations = read_str(0b00000100);
+        let mut spi = Mock::new(&expectations);
+        let mut driver = Ls7366::new_uninit(spi.clone());
         let result = driver.get_status().unwrap();
         assert_eq!(result, str_register::Str {
             cary: false,
@@ -110,30 +225,572 @@ mod tests {
             count_direction: str_register::Direction::Down,
             sign_bit: str_register::SignBit::Positive,
         });
+        spi.done();
     }
 
     #[test]
+    #[allow(clippy::useless_vec)]
     fn test_write_register() {
         let expectations = [
             // Dtr write
-            SpiTransaction::write(vec![InstructionRegister {
-                target: Target::Dtr,
-                action: Action::Write,
-            }.encode(), 0xBA, 0xAD, 0xBE, 0xEF],
-            ),
-
+            write(Target::Dtr, &[0xBA, 0xAD, 0xBE, 0xEF]),
             // mdr0 write
-            SpiTransaction::write(vec![InstructionRegister {
-                target: Target::Mdr0,
-                action: Action::Write,
-            }.encode(), 0xFD, 0xFD, 0xFD, 0xFD],
-            ),
-        ];
+            write(Target::Mdr0, &[0xFD, 0xFD, 0xFD, 0xFD]),
+        ].concat();
 
-        let spi = Mock::new(&expectations);
-        let mut driver = Ls7366::new_uninit(spi);
+        let mut spi = Mock::new(&expectations);
+        let mut driver = Ls7366::new_uninit(spi.clone());
 
         driver.write_register(Target::Dtr, &vec![0xBA, 0xAD, 0xBE, 0xEF]).unwrap();
         driver.write_register(Target::Mdr0, &vec![0xFD, 0xFD, 0xFD, 0xFD]).unwrap();
+        spi.done();
+    }
+
+    #[test]
+    fn test_configure() {
+        let mdr0 = Mdr0 {
+            quad_count_mode: QuadCountMode::Quad4x,
+            cycle_count_mode: CycleCountMode::FreeRunning,
+            index_mode: IndexMode::DisableIndex,
+            is_index_inverted: false,
+            filter_clock: FilterClockDivisionFactor::One,
+        };
+        let expectations = [
+            write_mdr0(0b00000011),
+            write_mdr1(CounterMode::Byte2),
+            // quad mode changed, everything else preserved.
+            write_mdr0(0b00000001),
+            write_mdr1(CounterMode::Byte1),
+        ].concat();
+        let mut spi = Mock::new(&expectations);
+        let mut driver = Ls7366::new_uninit(spi.clone());
+        assert_eq!(driver.mdr0(), Mdr0::default());
+        assert_eq!(driver.mdr1(), Mdr1::default());
+
+        let mdr1 = Mdr1 {
+            counter_mode: CounterMode::Byte2,
+            ..Mdr1::default()
+        };
+        driver.configure(mdr0, mdr1).unwrap();
+        assert_eq!(driver.mdr0(), mdr0);
+        assert_eq!(driver.mdr1(), mdr1);
+
+        driver.set_quad_mode(QuadCountMode::Quad1x).unwrap();
+        assert_eq!(driver.mdr0(), Mdr0 { quad_count_mode: QuadCountMode::Quad1x, ..mdr0 });
+
+        driver.set_counter_mode(CounterMode::Byte1).unwrap();
+        assert_eq!(driver.mdr1(), Mdr1 { counter_mode: CounterMode::Byte1, ..mdr1 });
+        spi.done();
+    }
+
+    #[test]
+    fn test_write_register_updates_configuration() {
+        let expectations = write_mdr0(0b00100110);
+        let mut spi = Mock::new(&expectations);
+        let mut driver = Ls7366::new_uninit(spi.clone());
+
+        driver.write_register(Target::Mdr0, &[0b00100110]).unwrap();
+        assert_eq!(driver.mdr0(), Mdr0 {
+            quad_count_mode: QuadCountMode::Quad2x,
+            cycle_count_mode: CycleCountMode::SingleCycle,
+            index_mode: IndexMode::ClearCntr,
+            is_index_inverted: false,
+            filter_clock: FilterClockDivisionFactor::One,
+        });
+        spi.done();
+    }
+
+    #[test]
+    fn test_read_mdr() {
+        let expectations = [
+            read_mdr(Target::Mdr0, 0b00100110),
+            read_mdr(Target::Mdr1, 0b00100001),
+        ].concat();
+        let mut spi = Mock::new(&expectations);
+        let mut driver = Ls7366::new_uninit(spi.clone());
+
+        assert_eq!(driver.read_mdr0().unwrap(), Mdr0 {
+            quad_count_mode: QuadCountMode::Quad2x,
+            cycle_count_mode: CycleCountMode::SingleCycle,
+            index_mode: IndexMode::ClearCntr,
+            is_index_inverted: false,
+            filter_clock: FilterClockDivisionFactor::One,
+        });
+        assert_eq!(driver.read_mdr1().unwrap(), Mdr1 {
+            counter_mode: CounterMode::Byte3,
+            flag_on_cmp: true,
+            ..Mdr1::default()
+        });
+        // Reading back never changes what the driver believes it configured.
+        assert_eq!(driver.mdr0(), Mdr0::default());
+        assert_eq!(driver.mdr1(), Mdr1::default());
+        spi.done();
+    }
+
+    #[test]
+    fn test_verify_configuration() {
+        let expectations = [
+            read_mdr(Target::Mdr0, 0x00),
+            read_mdr(Target::Mdr1, 0x00),
+            // floating MISO reads back as all ones.
+            read_mdr(Target::Mdr0, 0xFF),
+            read_mdr(Target::Mdr1, 0xFF),
+        ].concat();
+        let mut spi = Mock::new(&expectations);
+        let mut driver = Ls7366::new_uninit(spi.clone());
+
+        driver.verify_configuration().unwrap();
+        match driver.verify_configuration() {
+            Err(Error::ConfigurationMismatch(mismatch)) => {
+                assert_eq!(mismatch.expected_mdr0, Mdr0::default());
+                assert_eq!(mismatch.actual_mdr0.quad_count_mode, QuadCountMode::Quad4x);
+                assert_eq!(mismatch.expected_mdr1, Mdr1::default());
+                assert_eq!(mismatch.actual_mdr1.counter_mode, CounterMode::Byte1);
+            }
+            other => panic!("expected a configuration mismatch, got {:?}", other),
+        }
+        spi.done();
+    }
+
+    #[test]
+    fn test_write_dtr() {
+        let expectations = [
+            write_dtr(&[0xDE, 0xAD, 0xBE, 0xEF]),
+            write_mdr1(CounterMode::Byte2),
+            write_dtr(&[0xBE, 0xEF]),
+            write_mdr1(CounterMode::Byte3),
+            write_dtr(&[0x00, 0x00, 0x2A]),
+        ].concat();
+        let mut spi = Mock::new(&expectations);
+        let mut driver = Ls7366::new_uninit(spi.clone());
+        assert_eq!(driver.read_dtr(), 0);
+
+        driver.write_dtr(0xDEADBEEF).unwrap();
+        assert_eq!(driver.read_dtr(), 0xDEADBEEF);
+
+        driver.set_counter_mode(CounterMode::Byte2).unwrap();
+        driver.write_dtr(0xBEEF).unwrap();
+        assert_eq!(driver.read_dtr(), 0xBEEF);
+
+        driver.set_counter_mode(CounterMode::Byte3).unwrap();
+        driver.write_dtr(42).unwrap();
+        assert_eq!(driver.read_dtr(), 42);
+        spi.done();
+    }
+
+    #[test]
+    fn test_write_dtr_out_of_range() {
+        let expectations = write_mdr1(CounterMode::Byte1);
+        let mut spi = Mock::new(&expectations);
+        let mut driver = Ls7366::new_uninit(spi.clone());
+        driver.set_counter_mode(CounterMode::Byte1).unwrap();
+
+        match driver.write_dtr(0x100) {
+            Err(Error::ValueOutOfRange) => {}
+            other => panic!("expected ValueOutOfRange, got {:?}", other),
+        }
+        assert_eq!(driver.read_dtr(), 0);
+        spi.done();
+    }
+
+    #[test]
+    fn test_set_count() {
+        let expectations = [
+            write_dtr(&[0x00, 0x00, 0x01, 0x00]),
+            command(Target::Cntr, Action::Load),
+            write_mdr1(CounterMode::Byte2),
+            write_dtr(&[0xFF, 0xFE]),
+            command(Target::Cntr, Action::Load),
+        ].concat();
+        let mut spi = Mock::new(&expectations);
+        let mut driver = Ls7366::new_uninit(spi.clone());
+
+        driver.set_count(256).unwrap();
+        driver.set_counter_mode(CounterMode::Byte2).unwrap();
+        driver.set_count(-2).unwrap();
+        assert_eq!(driver.read_dtr(), 0xFFFE);
+        match driver.set_count(32768) {
+            Err(Error::ValueOutOfRange) => {}
+            other => panic!("expected ValueOutOfRange, got {:?}", other),
+        }
+        match driver.set_count(-32769) {
+            Err(Error::ValueOutOfRange) => {}
+            other => panic!("expected ValueOutOfRange, got {:?}", other),
+        }
+        spi.done();
+    }
+
+    #[test]
+    fn test_clear_count() {
+        let expectations = command(Target::Cntr, Action::Clear);
+        let mut spi = Mock::new(&expectations);
+        let mut driver = Ls7366::new_uninit(spi.clone());
+
+        driver.clear_count().unwrap();
+        spi.done();
+    }
+
+    #[test]
+    fn test_preset_count_on_index() {
+        let expectations = [
+            write_dtr(&[0xFF, 0xFF, 0xFF, 0x9C]),
+            write_mdr0(0b00010000),
+        ].concat();
+        let mut spi = Mock::new(&expectations);
+        let mut driver = Ls7366::new_uninit(spi.clone());
+
+        driver.preset_count_on_index(-100).unwrap();
+        assert_eq!(driver.mdr0().index_mode, IndexMode::LoadCntr);
+        spi.done();
+    }
+
+    #[test]
+    fn test_latched_count() {
+        let expectations = [
+            command(Target::Otr, Action::Load),
+            read_otr(&[0xFF, 0xFF, 0xFF, 0xF6]),
+            write_mdr1(CounterMode::Byte2),
+            read_otr(&[0x01, 0x00]),
+        ].concat();
+        let mut spi = Mock::new(&expectations);
+        let mut driver = Ls7366::new_uninit(spi.clone());
+
+        driver.latch_count().unwrap();
+        assert_eq!(driver.read_latched_count().unwrap(), -10);
+        driver.set_counter_mode(CounterMode::Byte2).unwrap();
+        assert_eq!(driver.read_latched_count().unwrap(), 256);
+        spi.done();
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let expectations = [
+            command(Target::Otr, Action::Load),
+            read_str(0b00001001),
+            read_otr(&[0xFF, 0xFF, 0xFF, 0xFE]),
+        ].concat();
+        let mut spi = Mock::new(&expectations);
+        let mut driver = Ls7366::new_uninit(spi.clone());
+
+        let snapshot = driver.snapshot().unwrap();
+        assert_eq!(snapshot.count, -2);
+        assert_eq!(snapshot.status.sign_bit, str_register::SignBit::Negative);
+        assert_eq!(snapshot.status.count_direction, str_register::Direction::Down);
+        assert!(snapshot.status.count_enabled);
+        spi.done();
+    }
+
+    #[test]
+    fn test_poll_events() {
+        let expectations = [
+            read_str(0b10111011),
+            command(Target::Str, Action::Clear),
+            // Nothing latched, so nothing to clear.
+            read_str(0b00001000),
+        ].concat();
+        let mut spi = Mock::new(&expectations);
+        let mut driver = Ls7366::new_uninit(spi.clone());
+
+        let events = driver.poll_events().unwrap();
+        assert_eq!(events, Events::CARRY | Events::COMPARE | Events::INDEX);
+        assert!(driver.poll_events().unwrap().is_empty());
+        spi.done();
+    }
+
+    #[test]
+    fn test_power_loss_recovery() {
+        let expectations = [
+            write_dtr(&[0xFF, 0xFF, 0xFF, 0xFE]),
+            command(Target::Cntr, Action::Load),
+            read_cntr(&[0x00, 0x00, 0x00, 0x05]),
+            read_str(0b00001000),
+            // Power lost: configuration, count and Dtr are restored before reading again.
+            read_cntr(&[0x00, 0x00, 0x00, 0x00]),
+            read_str(0b00000100),
+            write_mdr0(0x00),
+            write_mdr1(CounterMode::Byte4),
+            write_dtr(&[0x00, 0x00, 0x00, 0x05]),
+            command(Target::Cntr, Action::Load),
+            write_dtr(&[0xFF, 0xFF, 0xFF, 0xFE]),
+            command(Target::Str, Action::Clear),
+            read_cntr(&[0x00, 0x00, 0x00, 0x05]),
+            read_str(0b00001000),
+        ].concat();
+        let mut spi = Mock::new(&expectations);
+        let mut driver = Ls7366::new_uninit(spi.clone());
+        driver.set_supervision(Supervision { check_on_read: true, restore_count: true });
+
+        driver.set_count(-2).unwrap();
+        assert_eq!(driver.get_count().unwrap(), 5);
+        assert_eq!(driver.get_count().unwrap(), 5);
+        assert_eq!(driver.read_dtr(), 0xFFFFFFFE);
+        assert_eq!(driver.poll_events().unwrap(), Events::POWER_LOSS | Events::POWER_LOSS_RECOVERED);
+        spi.done();
+    }
+
+    #[test]
+    fn test_power_loss_survives_event_clear() {
+        let expectations = [
+            // Clearing the stale compare latch also clears the power-loss latch.
+            read_str(0b00100100),
+            command(Target::Str, Action::Clear),
+            write_dtr(&[0x00, 0x00, 0x01, 0x2C]),
+            write(Target::Mdr1, &[0b00100000]),
+            read_str(0b00000000),
+            write_mdr0(0x00),
+            write(Target::Mdr1, &[0b00100000]),
+            write_dtr(&[0x00, 0x00, 0x01, 0x2C]),
+            command(Target::Str, Action::Clear),
+            read_str(0b00000000),
+            read_str(0b00000000),
+        ].concat();
+        let mut spi = Mock::new(&expectations);
+        let mut driver = Ls7366::new_uninit(spi.clone());
+
+        driver.arm_compare(300).unwrap();
+        assert!(driver.check_power_loss().unwrap());
+        assert!(!driver.check_power_loss().unwrap());
+        assert_eq!(driver.poll_events().unwrap(), Events::POWER_LOSS | Events::POWER_LOSS_RECOVERED);
+        spi.done();
+    }
+
+    #[test]
+    fn test_power_loss_recovered_on_event_clear() {
+        let expectations = [
+            // Recovered instead of cleared.
+            read_str(0b00100100),
+            write_mdr0(0x00),
+            write_mdr1(CounterMode::Byte4),
+            write_dtr(&[0x00, 0x00, 0x00, 0x00]),
+            command(Target::Str, Action::Clear),
+            write_dtr(&[0x00, 0x00, 0x01, 0x2C]),
+            write(Target::Mdr1, &[0b00100000]),
+            read_str(0b00000000),
+        ].concat();
+        let mut spi = Mock::new(&expectations);
+        let mut driver = Ls7366::new_uninit(spi.clone());
+        driver.set_supervision(Supervision { check_on_read: true, restore_count: false });
+
+        driver.arm_compare(300).unwrap();
+        assert_eq!(driver.poll_events().unwrap(), Events::POWER_LOSS | Events::POWER_LOSS_RECOVERED);
+        spi.done();
+    }
+
+    #[test]
+    fn test_compare_trigger() {
+        let expectations = [
+            // The stale compare latch is discarded before the new position is written.
+            read_str(0b10100000),
+            command(Target::Str, Action::Clear),
+            write_dtr(&[0x00, 0x00, 0x01, 0x2C]),
+            write(Target::Mdr1, &[0b00100000]),
+            read_str(0b00000000),
+            read_str(0b00000000),
+            read_str(0b00110000),
+            command(Target::Str, Action::Clear),
+            read_str(0b00000000),
+        ].concat();
+        let mut spi = Mock::new(&expectations);
+        let mut driver = Ls7366::new_uninit(spi.clone());
+
+        driver.arm_compare(300).unwrap();
+        assert_eq!(driver.read_dtr(), 300);
+        assert!(driver.mdr1().flag_on_cmp);
+        assert!(!driver.check_compare().unwrap());
+        driver.wait_for_compare().unwrap();
+        // The stale compare latch is dropped, the rest is kept for polling.
+        assert_eq!(driver.poll_events().unwrap(), Events::CARRY | Events::INDEX);
+        spi.done();
+    }
+
+    #[test]
+    fn test_synchronized_axes() {
+        let axis_0 = [
+            write_mdr0(0b00110000),
+            // A stale index latch is discarded.
+            read_str(0b00010000),
+            command(Target::Str, Action::Clear),
+            read_str(0b00010000),
+            command(Target::Str, Action::Clear),
+            read_otr(&[0x00, 0x00, 0x01, 0x00]),
+            write_mdr0(0x00),
+        ].concat();
+        let axis_1 = [
+            write_mdr0(0b00110000),
+            read_str(0b00000000),
+            read_str(0b00000000),
+            read_str(0b00010000),
+            command(Target::Str, Action::Clear),
+            read_otr(&[0xFF, 0xFF, 0xFF, 0x00]),
+            write_mdr0(0x00),
+        ].concat();
+        let mut spi_0 = Mock::new(&axis_0);
+        let mut spi_1 = Mock::new(&axis_1);
+        let drivers = [Ls7366::new_uninit(spi_0.clone()), Ls7366::new_uninit(spi_1.clone())];
+
+        let mut axes = SynchronizedAxes::new(drivers).map_err(|(error, _)| error).unwrap();
+        assert_eq!(axes.poll().unwrap(), None);
+        assert_eq!(axes.poll().unwrap(), Some([256, -256]));
+
+        let drivers = axes.release().map_err(|(error, _)| error).unwrap();
+        assert_eq!(drivers[0].mdr0().index_mode, IndexMode::DisableIndex);
+        spi_0.done();
+        spi_1.done();
+    }
+
+    #[test]
+    fn test_shared_bus() {
+        // Bus level expectations carry no transaction framing, the select pins provide it.
+        let expectations = [
+            SpiTransaction::write_vec(vec![InstructionRegister {
+                target: Target::Cntr,
+                action: Action::Read,
+            }.encode()]),
+            SpiTransaction::read_vec(vec![0x00, 0x00, 0x00, 0x2A]),
+            SpiTransaction::flush(),
+            SpiTransaction::write_vec(vec![InstructionRegister {
+                target: Target::Cntr,
+                action: Action::Clear,
+            }.encode()]),
+            SpiTransaction::flush(),
+            SpiTransaction::write_vec(vec![InstructionRegister {
+                target: Target::Cntr,
+                action: Action::Read,
+            }.encode()]),
+            SpiTransaction::read_vec(vec![0x00, 0x00, 0x00, 0x2B]),
+            SpiTransaction::flush(),
+        ];
+        let x_pin = [
+            PinTransaction::set(PinState::High),
+            PinTransaction::set(PinState::Low),
+            PinTransaction::set(PinState::High),
+            PinTransaction::set(PinState::Low),
+            PinTransaction::set(PinState::High),
+        ];
+        let y_pin = [
+            PinTransaction::set(PinState::High),
+            PinTransaction::set(PinState::Low),
+            PinTransaction::set(PinState::High),
+        ];
+        let mut spi = Mock::new(&expectations);
+        let mut x_select = PinMock::new(&x_pin);
+        let mut y_select = PinMock::new(&y_pin);
+
+        let bus = SharedBus::new(spi.clone());
+        let mut x_axis = Ls7366::new_uninit(bus.device(x_select.clone()).unwrap());
+        let mut y_axis = Ls7366::new_uninit(bus.device(y_select.clone()).unwrap());
+
+        assert_eq!(x_axis.get_count().unwrap(), 42);
+        y_axis.clear_count().unwrap();
+        assert_eq!(x_axis.get_count().unwrap(), 43);
+
+        spi.done();
+        x_select.done();
+        y_select.done();
+    }
+
+    #[test]
+    fn test_shared_bus_rejects_delay() {
+        let mut spi = Mock::new(&[]);
+        // Only driven high when the device is created, never selected.
+        let mut select = PinMock::new(&[PinTransaction::set(PinState::High)]);
+
+        let bus = SharedBus::new(spi.clone());
+        let mut device = bus.device(select.clone()).unwrap();
+        let result = device.transaction(&mut [Operation::Write(&[0x00]), Operation::DelayNs(100)]);
+        assert!(matches!(result, Err(BusError::DelayUnsupported)));
+
+        spi.done();
+        select.done();
+    }
+
+    /// A bus whose writes fail, logging what is done to it alongside the select pin.
+    struct FailingBus<'a> {
+        log: &'a RefCell<Vec<&'static str>>,
+    }
+
+    impl spi::ErrorType for FailingBus<'_> {
+        type Error = spi::ErrorKind;
+    }
+
+    impl SpiBus<u8> for FailingBus<'_> {
+        fn read(&mut self, _words: &mut [u8]) -> Result<(), spi::ErrorKind> {
+            Ok(())
+        }
+
+        fn write(&mut self, _words: &[u8]) -> Result<(), spi::ErrorKind> {
+            self.log.borrow_mut().push("write");
+            Err(spi::ErrorKind::Other)
+        }
+
+        fn transfer(&mut self, _read: &mut [u8], _write: &[u8]) -> Result<(), spi::ErrorKind> {
+            Ok(())
+        }
+
+        fn transfer_in_place(&mut self, _words: &mut [u8]) -> Result<(), spi::ErrorKind> {
+            Ok(())
+        }
+
+        fn flush(&mut self) -> Result<(), spi::ErrorKind> {
+            self.log.borrow_mut().push("flush");
+            Ok(())
+        }
+    }
+
+    struct LoggingPin<'a> {
+        log: &'a RefCell<Vec<&'static str>>,
+    }
+
+    impl digital::ErrorType for LoggingPin<'_> {
+        type Error = Infallible;
+    }
+
+    impl OutputPin for LoggingPin<'_> {
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            self.log.borrow_mut().push("low");
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            self.log.borrow_mut().push("high");
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_shared_bus_flushes_before_deselecting_on_error() {
+        let log = RefCell::new(Vec::new());
+        let bus = SharedBus::new(FailingBus { log: &log });
+        let mut device = bus.device(LoggingPin { log: &log }).unwrap();
+
+        let result = device.transaction(&mut [Operation::Write(&[0x00]), Operation::Write(&[0x01])]);
+        assert!(matches!(result, Err(BusError::Spi(spi::ErrorKind::Other))));
+        assert_eq!(*log.borrow(), ["high", "low", "write", "flush", "high"]);
+    }
+
+    #[test]
+    fn test_clock_direction_profile() {
+        let expectations = [
+            write_mdr0(0x00),
+            write_mdr1(CounterMode::Byte4),
+            read_cntr(&[0x00, 0x00, 0x00, 0x20]),
+            // Counting up, enabled.
+            read_str(0b0000_1010),
+            read_cntr(&[0xFF, 0xFF, 0xFF, 0xF0]),
+            // Counting down and negative.
+            read_str(0b0000_1001),
+        ].concat();
+        let mut spi = Mock::new(&expectations);
+        let mut driver = Ls7366::new_uninit(spi.clone());
+
+        driver.configure(clock_direction::MDR0, clock_direction::MDR1).unwrap();
+        assert_eq!(driver.mdr0().quad_count_mode, QuadCountMode::NonQuad);
+        assert_eq!(driver.get_count().unwrap(), 32);
+        assert_eq!(driver.get_status().unwrap().count_direction, str_register::Direction::Up);
+        assert_eq!(driver.get_count().unwrap(), -16);
+        let status = driver.get_status().unwrap();
+        assert_eq!(status.count_direction, str_register::Direction::Down);
+        assert_eq!(status.sign_bit, str_register::SignBit::Negative);
+        spi.done();
     }
 }
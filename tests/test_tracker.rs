@@ -0,0 +1,126 @@
+#[cfg(test)]
+mod tests {
+    use core::cell::RefCell;
+
+    use ls7366::emulator::{EmulatorDevice, Ls7366Emulator};
+    use ls7366::events::Events;
+    use ls7366::generator::{ConstantVelocity, Jitter, QuadratureGenerator, Signalling, Trapezoidal};
+    use ls7366::{Error, Ls7366};
+    use ls7366::mdr0::{CycleCountMode, Mdr0, QuadCountMode};
+    use ls7366::mdr1::{CounterMode, Mdr1};
+    use ls7366::tracker::PositionTracker;
+
+    fn driver(chip: &RefCell<Ls7366Emulator>, counter_mode: CounterMode) -> Ls7366<EmulatorDevice<'_>> {
+        let mut driver = Ls7366::new(EmulatorDevice::new(chip)).unwrap();
+        let mdr0 = Mdr0 { quad_count_mode: QuadCountMode::Quad4x, ..Mdr0::default() };
+        driver.configure(mdr0, Mdr1 { counter_mode, ..Mdr1::default() }).unwrap();
+        driver
+    }
+
+    /// Follows `profile`, updating the tracker after every tick.
+    fn follow<P: Iterator<Item = i64>>(chip: &RefCell<Ls7366Emulator>, tracker: &mut PositionTracker<EmulatorDevice<'_>>, generator: &mut QuadratureGenerator, profile: P) {
+        for steps in profile {
+            for inputs in generator.run(core::iter::once(steps)) {
+                chip.borrow_mut().apply(inputs);
+            }
+            assert_eq!(tracker.update().unwrap(), generator.position());
+        }
+    }
+
+    #[test]
+    fn test_tracks_across_many_wraps() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut tracker = PositionTracker::new(driver(&chip, CounterMode::Byte1)).unwrap();
+        let mut generator = QuadratureGenerator::new(Signalling::Quadrature);
+
+        follow(&chip, &mut tracker, &mut generator, ConstantVelocity::new(100, 100));
+        assert_eq!(tracker.position(), 10_000);
+        follow(&chip, &mut tracker, &mut generator, Trapezoidal::new(-25_000, 120, 7));
+        assert_eq!(tracker.position(), -15_000);
+    }
+
+    #[test]
+    fn test_tracks_reversals_with_jitter() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut tracker = PositionTracker::new(driver(&chip, CounterMode::Byte1)).unwrap();
+        let mut generator = QuadratureGenerator::new(Signalling::Quadrature);
+
+        let profile = ConstantVelocity::new(90, 20)
+            .chain(ConstantVelocity::new(-110, 40))
+            .chain(ConstantVelocity::new(60, 15));
+        follow(&chip, &mut tracker, &mut generator, Jitter::new(profile, 10, 7));
+        assert_eq!(tracker.position(), 1800 - 4400 + 900);
+    }
+
+    #[test]
+    fn test_wrap_latches_resolve_large_moves() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut tracker = PositionTracker::new(driver(&chip, CounterMode::Byte1)).unwrap();
+        let mut generator = QuadratureGenerator::new(Signalling::Quadrature);
+
+        // More than half the 1-byte range per update, across the wrap point: only resolvable
+        // through the carry and borrow latches.
+        let profile = ConstantVelocity::new(100, 1)
+            .chain(ConstantVelocity::new(200, 1))
+            .chain(ConstantVelocity::new(-230, 1));
+        follow(&chip, &mut tracker, &mut generator, profile);
+        assert_eq!(tracker.position(), 70);
+    }
+
+    #[test]
+    fn test_keeps_other_events_for_polling() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut tracker = PositionTracker::new(driver(&chip, CounterMode::Byte1)).unwrap();
+        let mut generator = QuadratureGenerator::new(Signalling::Quadrature);
+
+        // Coming back to zero matches Dtr and latches a compare event.
+        follow(&chip, &mut tracker, &mut generator, ConstantVelocity::new(10, 1));
+        follow(&chip, &mut tracker, &mut generator, ConstantVelocity::new(-10, 1));
+        assert_eq!(tracker.driver().poll_events().unwrap(), Events::COMPARE);
+    }
+
+    #[test]
+    fn test_starts_from_current_count() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut driver = driver(&chip, CounterMode::Byte2);
+        driver.set_count(-5).unwrap();
+        let mut tracker = PositionTracker::new(driver).unwrap();
+        assert_eq!(tracker.position(), -5);
+
+        let mut generator = QuadratureGenerator::new(Signalling::Quadrature);
+        for inputs in generator.run(ConstantVelocity::new(-20_000, 1)) {
+            chip.borrow_mut().apply(inputs);
+        }
+        assert_eq!(tracker.update().unwrap(), -20_005);
+
+        tracker.set_position(0);
+        for inputs in generator.run(ConstantVelocity::new(30_000, 1)) {
+            chip.borrow_mut().apply(inputs);
+        }
+        assert_eq!(tracker.update().unwrap(), 30_000);
+        assert_eq!(tracker.release().get_count().unwrap(), 9_995);
+    }
+
+    #[test]
+    fn test_rejects_bounded_counters() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut driver = driver(&chip, CounterMode::Byte1);
+        driver.configure_modulo(100).unwrap();
+        assert!(matches!(PositionTracker::new(driver), Err(Error::UnsupportedCycleCountMode)));
+
+        let mut driver = Ls7366::new(EmulatorDevice::new(&chip)).unwrap();
+        driver.set_cycle_count_mode(CycleCountMode::SingleCycle).unwrap();
+        assert!(matches!(PositionTracker::new(driver), Err(Error::UnsupportedCycleCountMode)));
+    }
+
+    #[test]
+    fn test_power_loss_seen_by_update_is_recovered() {
+        let chip = RefCell::new(Ls7366Emulator::new());
+        let mut tracker = PositionTracker::new(driver(&chip, CounterMode::Byte1)).unwrap();
+
+        chip.borrow_mut().power_cycle();
+        assert_eq!(tracker.update().unwrap(), 0);
+        assert!(tracker.driver().check_power_loss().unwrap());
+        assert_eq!(chip.borrow().mdr1().counter_mode, CounterMode::Byte1);
+    }
+}
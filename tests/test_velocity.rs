@@ -0,0 +1,116 @@
+#[cfg(test)]
+mod tests {
+    use ls7366::mdr1::CounterMode;
+    use ls7366::velocity::{AlphaBeta, Estimator, FiniteDifference, LeastSquares, Motion, MotionEstimator};
+
+    /// Samples `position(t)` every `period_us` and returns the last estimate.
+    fn run<E: Estimator>(estimator: E, counter_mode: CounterMode, period_us: u64, samples: u64, position: impl Fn(f32) -> f32) -> Motion {
+        let mut estimator = MotionEstimator::new(estimator, counter_mode);
+        let bits = 8 * counter_mode.byte_count() as u32;
+        let mut motion = None;
+        for sample in 0..samples {
+            let timestamp = sample * period_us;
+            let unwrapped = position(timestamp as f32 * 1e-6).round() as i64;
+            // What get_count returns: the low bits, sign-extended.
+            let count = (unwrapped << (64 - bits)) >> (64 - bits);
+            motion = estimator.update(timestamp, count);
+        }
+        motion.unwrap()
+    }
+
+    fn assert_close(actual: f32, expected: f32, tolerance: f32) {
+        assert!((actual - expected).abs() <= tolerance, "{} is not within {} of {}", actual, tolerance, expected);
+    }
+
+    #[test]
+    fn test_needs_two_samples() {
+        let mut estimator = MotionEstimator::new(FiniteDifference::new(), CounterMode::Byte4);
+        assert_eq!(estimator.update(0, 10), None);
+        let motion = estimator.update(1000, 20).unwrap();
+        assert_eq!(motion, Motion { position: 20, velocity: 10_000.0, acceleration: 0.0 });
+
+        // Stale samples are ignored.
+        assert_eq!(estimator.update(1000, 500), Some(motion));
+        assert_eq!(estimator.update(500, 500), Some(motion));
+
+        estimator.reset();
+        assert_eq!(estimator.update(2000, 30), None);
+        assert_eq!(estimator.motion(), None);
+    }
+
+    #[test]
+    fn test_estimators_reject_non_increasing_timestamps() {
+        fn check<E: Estimator>(mut estimator: E) {
+            for sample in 0..4u64 {
+                estimator.update(1000 * (sample + 1), 10 * sample as i64);
+            }
+            // Repeated and earlier timestamps are rejected without disturbing the state.
+            assert_eq!(estimator.update(4000, 500), None);
+            assert_eq!(estimator.update(1500, 500), None);
+            let motion = estimator.update(5000, 40).unwrap();
+            assert!(motion.velocity.is_finite() && motion.acceleration.is_finite());
+            assert!((motion.velocity - 10_000.0).abs() < 1.0, "{:?}", motion);
+        }
+        check(FiniteDifference::new());
+        check(LeastSquares::<4>::new());
+        check(AlphaBeta::new(1.0, 1.0));
+    }
+
+    #[test]
+    fn test_constant_velocity() {
+        let ramp = |t: f32| -3000.0 * t;
+        let motion = run(FiniteDifference::new(), CounterMode::Byte4, 1000, 50, ramp);
+        assert_close(motion.velocity, -3000.0, 1e-2);
+        assert_close(motion.acceleration, 0.0, 1e-2);
+
+        let motion = run(LeastSquares::<10>::new(), CounterMode::Byte4, 1000, 50, ramp);
+        assert_eq!(motion.position, -147);
+        assert_close(motion.velocity, -3000.0, 1.0);
+        assert_close(motion.acceleration, 0.0, 100.0);
+
+        let motion = run(AlphaBeta::new(0.5, 0.1), CounterMode::Byte4, 1000, 200, ramp);
+        assert_close(motion.velocity, -3000.0, 1.0);
+        assert_close(motion.acceleration, 0.0, 100.0);
+    }
+
+    #[test]
+    fn test_constant_acceleration() {
+        let parabola = |t: f32| 50_000.0 * t + 2_000_000.0 * t * t;
+        // At t = 0.049s: velocity 246000, acceleration 4000000.
+        let motion = run(LeastSquares::<16>::new(), CounterMode::Byte4, 1000, 50, parabola);
+        assert_close(motion.velocity, 246_000.0, 500.0);
+        assert_close(motion.acceleration, 4_000_000.0, 40_000.0);
+
+        let motion = run(FiniteDifference::new(), CounterMode::Byte4, 5000, 11, parabola);
+        // Differences lag by half a sample period.
+        assert_close(motion.velocity, 50_000.0 + 4_000_000.0 * 0.0475, 200.0);
+        assert_close(motion.acceleration, 4_000_000.0, 40_000.0);
+    }
+
+    #[test]
+    fn test_counter_wrap() {
+        // Crosses the 1-byte range many times over.
+        let ramp = |t: f32| 50_000.0 * t;
+        let motion = run(FiniteDifference::new(), CounterMode::Byte1, 1000, 100, ramp);
+        assert_eq!(motion.position, 4950);
+        assert_close(motion.velocity, 50_000.0, 1.0);
+
+        let motion = run(LeastSquares::<8>::new(), CounterMode::Byte1, 1000, 100, ramp);
+        assert_close(motion.velocity, 50_000.0, 10.0);
+
+        let motion = run(AlphaBeta::new(0.5, 0.2), CounterMode::Byte2, 1000, 200, |t| -100_000.0 * t);
+        assert_eq!(motion.position, -19_900);
+        assert_close(motion.velocity, -100_000.0, 10.0);
+    }
+
+    #[test]
+    fn test_alpha_beta_smooths_quantisation() {
+        // 0.3 counts per sample: raw differences jump between 0 and 1000 counts/s.
+        let slow = |t: f32| 300.0 * t;
+        let motion = run(FiniteDifference::new(), CounterMode::Byte4, 1000, 500, slow);
+        assert!(motion.velocity.abs() < 1.0 || (motion.velocity - 1000.0).abs() < 1.0);
+
+        let motion = run(AlphaBeta::new(0.1, 0.005), CounterMode::Byte4, 1000, 500, slow);
+        assert_close(motion.velocity, 300.0, 60.0);
+    }
+}